@@ -4,19 +4,17 @@
 // https://www.boost.org/LICENSE_1_0.txt)
 
 use std::{ mem, rc::Rc };
-use OpenGL;
+use { Binding, OpenGL };
 use types::*;
 
-static mut CURRENT_BUFFER: GLuint = ::std::u32::MAX; // No current buffer
-
 /// An OpenGL buffer, usually a VBO.
 #[derive(Clone)] pub struct Buffer(pub(crate) Rc<BufferContext>);
 
 impl Buffer {
 	/// Create a new buffer
 	pub fn new(opengl: &OpenGL) -> Self {
-		let mut buffers = [unsafe { mem::uninitialized() }];
-		gl!(opengl, (opengl.get().gen_buffers)(1/*1 buffer*/,
+		let mut buffers = [0];
+		gl!(opengl, gen_buffers(1/*1 buffer*/,
 			buffers.as_mut_ptr()));
 		Buffer(Rc::new(BufferContext(buffers[0], opengl.clone())))
 	}
@@ -25,24 +23,24 @@ impl Buffer {
 	pub(crate) fn bind(&self) {
 		let buffer = self.get();
 
-		if buffer != unsafe { CURRENT_BUFFER } {
-			gl!((*self.0).1, ((*self.0).1.get().bind_buffer)(
+		if buffer != self.0 .1.get().bound_buffer.get() {
+			gl!(self.0 .1, bind_buffer(
 				GL_ARRAY_BUFFER, buffer));
-			unsafe { CURRENT_BUFFER = buffer; }
+			self.0 .1.get().bound_buffer.set(buffer);
 		}
 	}
 
 	/// Set the bound buffer's data
 	pub fn set<T>(&self, data: &[T]) {
 		self.bind();
-		gl!((*self.0).1, ((*self.0).1.get().buffer_data)(
+		gl!(self.0 .1, buffer_data(
 			GL_ARRAY_BUFFER,
-			(data.len() * mem::size_of::<T>()) as isize,
+			mem::size_of_val(data) as isize,
 			data.as_ptr() as *const _, GL_DYNAMIC_DRAW));
 	}
 
 	pub(crate) fn get(&self) -> GLuint {
-		(*self.0).0
+		self.0 .0
 	}
 }
 
@@ -50,6 +48,10 @@ pub struct BufferContext(pub(crate) GLuint, pub(crate) OpenGL);
 
 impl Drop for BufferContext {
 	fn drop(&mut self) {
-		gl!(self.1, (self.1.get().delete_buffer)(1, [self.0].as_ptr()));
+		if self.1.is_destroyed() {
+			return;
+		}
+		self.1.forget_binding(Binding::Buffer, self.0);
+		gl!(self.1, delete_buffer(1, [self.0].as_ptr()));
 	}
 }
@@ -0,0 +1,200 @@
+// Copyright Jeron A. Lau 2018.
+// Dual-licensed under either the MIT License or the Boost Software License,
+// Version 1.0.  (See accompanying file LICENSE_1_0.txt or copy at
+// https://www.boost.org/LICENSE_1_0.txt)
+
+use c_void;
+
+/// A desktop OpenGL profile.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Profile {
+	Core,
+	Compatibility,
+}
+
+/// A client API and version to create a context for.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Version {
+	/// OpenGL ES major.minor
+	GlEs(u8, u8),
+	/// Desktop OpenGL major.minor, the profile is ignored before 3.2.
+	Gl(u8, u8, Profile),
+}
+
+impl Version {
+	/// If this is OpenGL ES 3.0 or newer.
+	pub fn is_gles3(&self) -> bool {
+		match *self {
+			Version::GlEs(major, _) => major >= 3,
+			Version::Gl(..) => false,
+		}
+	}
+
+	/// If this is at least OpenGL ES `es` or desktop OpenGL `gl`.
+	pub(crate) fn at_least(&self, es: (u8, u8), gl: (u8, u8)) -> bool {
+		match *self {
+			Version::GlEs(major, minor) => (major, minor) >= es,
+			Version::Gl(major, minor, _) => (major, minor) >= gl,
+		}
+	}
+
+	/// Parse a `GL_VERSION` string, like "OpenGL ES 3.2 Mesa 18.0.5" or
+	/// "4.5.0 NVIDIA 390.48".  Desktop versions get `profile`, unless the
+	/// string says it's a core profile.
+	pub(crate) fn parse(string: &str, profile: Profile) -> Option<Version> {
+		let (es, rest) = match string.find("OpenGL ES") {
+			Some(i) => (true, &string[i + "OpenGL ES".len()..]),
+			None => (false, string),
+		};
+		// Skip a profile like "-CM" (OpenGL ES 1.x).
+		let number = rest
+			.trim_start_matches(|c: char| !c.is_ascii_digit());
+		let mut parts = number.split(|c: char| !c.is_ascii_digit());
+		let major = parts.next()?.parse().ok()?;
+		let minor = parts.next()?.parse().ok()?;
+
+		Some(if es {
+			Version::GlEs(major, minor)
+		} else if string.contains("Core Profile") {
+			Version::Gl(major, minor, Profile::Core)
+		} else {
+			Version::Gl(major, minor, profile)
+		})
+	}
+
+	/// The `EGL_RENDERABLE_TYPE` bit a config needs for this version.
+	pub(crate) fn renderable(&self) -> i32 {
+		match *self {
+			Version::GlEs(major, _) if major >= 3 => 0x0040, // ES3
+			Version::GlEs(..) => 0x0004, // EGL_OPENGL_ES2_BIT
+			Version::Gl(..) => 0x0008, // EGL_OPENGL_BIT
+		}
+	}
+}
+
+/// Which native platform to connect to.  Other than `Default`, these use
+/// `eglGetPlatformDisplayEXT()` and need the matching EGL extension.
+/// Ignored on Windows.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Platform {
+	/// Let the driver decide (`eglGetDisplay()`).  Headless contexts try
+	/// `Surfaceless` first.
+	Default,
+	/// An X11 `Display*`, or null for the default X display.
+	X11(*mut c_void),
+	/// A Wayland `wl_display*`.
+	Wayland(*mut c_void),
+	/// A `gbm_device*`, for rendering straight to DRM.
+	Gbm(*mut c_void),
+	/// Mesa's surfaceless platform, which needs no native display.
+	Surfaceless,
+	/// An `EGLDeviceEXT` from `OpenGLBuilder::devices()`.
+	Device(*mut c_void),
+}
+
+/// The requested framebuffer configuration.  Sizes are minimums, the config
+/// with the highest `score` out of all matching configs is chosen.
+#[derive(Clone, Debug)]
+pub struct Config {
+	/// Bits in the alpha channel.  Default: 0
+	pub alpha: u8,
+	/// Bits in the depth buffer.  Default: 24
+	pub depth: u8,
+	/// Bits in the stencil buffer.  Default: 8
+	pub stencil: u8,
+	/// Samples per pixel for multisampling, 0 to disable.  Default: 0
+	pub samples: u8,
+	/// Versions to try creating a context for, most preferred first.
+	/// Default: `[Version::GlEs(2, 0)]`
+	pub versions: Vec<Version>,
+	/// Native platform to connect to.  Default: `Platform::Default`
+	pub platform: Platform,
+	/// How good a matching config is, higher is better.  Default:
+	/// `Config::score_default`
+	pub score: fn(&Config, &ConfigInfo) -> i32,
+}
+
+impl Default for Config {
+	fn default() -> Config {
+		Config {
+			alpha: 0,
+			depth: 24,
+			stencil: 8,
+			samples: 0,
+			versions: vec![Version::GlEs(2, 0)],
+			platform: Platform::Default,
+			score: Config::score_default,
+		}
+	}
+}
+
+impl Config {
+	/// Prefer configs that aren't slow, then those with the fewest bits
+	/// over what was requested.
+	pub fn score_default(&self, info: &ConfigInfo) -> i32 {
+		let extra = (info.red + info.green + info.blue - 24)
+			+ (info.alpha - self.alpha as i32)
+			+ (info.depth - self.depth as i32)
+			+ (info.stencil - self.stencil as i32)
+			+ (info.samples - self.samples as i32);
+
+		if info.slow {
+			-1000 - extra
+		} else {
+			-extra
+		}
+	}
+}
+
+/// A framebuffer config that the driver supports.
+#[derive(Clone, Debug)]
+pub struct ConfigInfo {
+	/// The driver's ID for this config (`EGL_CONFIG_ID`).
+	pub id: i32,
+	pub red: i32,
+	pub green: i32,
+	pub blue: i32,
+	pub alpha: i32,
+	pub depth: i32,
+	pub stencil: i32,
+	pub samples: i32,
+	/// If the driver marks this config as slow (`EGL_SLOW_CONFIG`).
+	pub slow: bool,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(string: &str) -> Option<Version> {
+		Version::parse(string, Profile::Compatibility)
+	}
+
+	#[test]
+	fn parse_gles() {
+		assert_eq!(parse("OpenGL ES 3.2 Mesa 18.0.5"),
+			Some(Version::GlEs(3, 2)));
+		assert_eq!(parse("OpenGL ES 2.0 build 1.4@2722815"),
+			Some(Version::GlEs(2, 0)));
+		assert_eq!(parse("OpenGL ES-CM 1.1"),
+			Some(Version::GlEs(1, 1)));
+	}
+
+	#[test]
+	fn parse_desktop() {
+		// NVIDIA doesn't name the profile, so it's the one given.
+		assert_eq!(Version::parse("4.6.0 NVIDIA 535.54.03",
+			Profile::Core), Some(Version::Gl(4, 6, Profile::Core)));
+		assert_eq!(parse("4.6 (Core Profile) Mesa 24.0.5"),
+			Some(Version::Gl(4, 6, Profile::Core)));
+		assert_eq!(parse("2.1 Mesa 10.1.3"),
+			Some(Version::Gl(2, 1, Profile::Compatibility)));
+	}
+
+	#[test]
+	fn parse_invalid() {
+		assert_eq!(parse(""), None);
+		assert_eq!(parse("OpenGL ES"), None);
+		assert_eq!(parse("4"), None);
+	}
+}
@@ -0,0 +1,285 @@
+// Copyright Jeron A. Lau 2018.
+// Dual-licensed under either the MIT License or the Boost Software License,
+// Version 1.0.  (See accompanying file LICENSE_1_0.txt or copy at
+// https://www.boost.org/LICENSE_1_0.txt)
+
+use std::fmt;
+use types::*;
+
+/// A shader stage.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Stage {
+	Vertex,
+	Fragment,
+	/// Linking the shaders together.
+	Link,
+}
+
+impl Stage {
+	/// The shader type to pass to `glCreateShader()`.  Panics for `Link`.
+	pub(crate) fn gl_enum(self) -> GLenum {
+		match self {
+			Stage::Vertex => 0x8B31, // GL_VERTEX_SHADER
+			Stage::Fragment => 0x8B30, // GL_FRAGMENT_SHADER
+			Stage::Link => panic!("Linking isn't a shader stage"),
+		}
+	}
+}
+
+impl fmt::Display for Stage {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match *self {
+			Stage::Vertex => "vertex",
+			Stage::Fragment => "fragment",
+			Stage::Link => "link",
+		})
+	}
+}
+
+/// How bad a diagnostic is.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Severity {
+	Error,
+	Warning,
+	/// Anything else the driver printed.
+	Info,
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match *self {
+			Severity::Error => "error",
+			Severity::Warning => "warning",
+			Severity::Info => "info",
+		})
+	}
+}
+
+/// A message from the shader compiler.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+	pub stage: Stage,
+	/// The file the line is in, for preprocessed shaders.
+	pub file: Option<String>,
+	/// The line in the shader source (starting at 1), if the driver gave
+	/// one.
+	pub line: Option<u32>,
+	/// The column in the line (starting at 1), if the driver gave one.
+	pub column: Option<u32>,
+	pub severity: Severity,
+	pub message: String,
+	/// The source code on `line`.
+	pub source: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.stage {
+			Stage::Link => write!(f, "program")?,
+			stage => write!(f, "{} shader", stage)?,
+		}
+		if let Some(ref file) = self.file {
+			write!(f, " {}:", file)?;
+		}
+		let space = if self.file.is_some() { "" } else { " " };
+		match (self.line, self.column) {
+			(Some(line), Some(column)) => write!(f, "{}{}:{}", space,
+				line, column)?,
+			(Some(line), None) => write!(f, "{}{}", space, line)?,
+			_ => {}
+		}
+		write!(f, ": {}: {}", self.severity, self.message)?;
+		if let Some(ref source) = self.source {
+			write!(f, "\n    {}", source.trim())?;
+		}
+		Ok(())
+	}
+}
+
+/// Parse a shader info log into diagnostics.  Lines that don't match a known
+/// format are kept as `default` severity diagnostics without a location.
+pub(crate) fn parse(stage: Stage, log: &str, source: &[u8],
+	default: Severity) -> Vec<Diagnostic>
+{
+	let source = String::from_utf8_lossy(source);
+
+	log.lines().filter(|line| !line.trim().is_empty()).map(|text| {
+		let (severity, line, column, message) = parse_line(text.trim());
+		let source = line.and_then(|line| {
+			source.lines().nth((line as usize).checked_sub(1)?)
+		}).map(str::to_string);
+
+		Diagnostic {
+			stage,
+			file: None,
+			line,
+			column,
+			severity: severity.unwrap_or(default),
+			message: message.to_string(),
+			source,
+		}
+	}).collect()
+}
+
+/// Parse one line of a log.  Understands:
+/// * Mesa: `0:12(5): error: message`
+/// * NVIDIA: `0(12) : error C0000: message`
+/// * Adreno, Mali and glslang: `ERROR: 0:12: message`
+fn parse_line(text: &str)
+	-> (Option<Severity>, Option<u32>, Option<u32>, &str)
+{
+	// Adreno & Mali start with the severity.
+	let (mut severity, rest) = match split_severity(text) {
+		Some((severity, rest)) => (Some(severity), rest),
+		None => (None, text),
+	};
+
+	let (line, column, rest) = match split_location(rest) {
+		Some(location) => location,
+		None => return (severity, None, None, rest),
+	};
+
+	// Mesa & NVIDIA have the severity after the location.
+	if severity.is_none() {
+		if let Some((s, message)) = split_severity(rest) {
+			severity = Some(s);
+			return (severity, Some(line), column, message);
+		}
+	}
+
+	(severity, Some(line), column, rest)
+}
+
+/// Split `error: message` or `error C0000: message` into the severity and
+/// the message.
+fn split_severity(text: &str) -> Option<(Severity, &str)> {
+	let colon = text.find(':')?;
+	let word = text[..colon].split_whitespace().next()?.to_lowercase();
+	let severity = match word.as_str() {
+		"error" | "fatal" => Severity::Error,
+		"warning" => Severity::Warning,
+		"info" | "note" => Severity::Info,
+		_ => return None,
+	};
+
+	Some((severity, text[colon + 1..].trim_start()))
+}
+
+/// Split `0:12(5): `, `0:12: ` or `0(12) : ` into the line, column and
+/// the rest.
+fn split_location(text: &str) -> Option<(u32, Option<u32>, &str)> {
+	// Skip the source string number.
+	let (_, rest) = split_number(text)?;
+
+	if let Some(rest) = rest.strip_prefix(':') {
+		// Mesa & glslang
+		let (line, rest) = split_number(rest)?;
+		let (column, rest) = match rest.strip_prefix('(') {
+			Some(rest) => {
+				let (column, rest) = split_number(rest)?;
+				(Some(column), rest.strip_prefix(')')?)
+			}
+			None => (None, rest),
+		};
+		let rest = rest.strip_prefix(':')?;
+
+		Some((line, column, rest.trim_start()))
+	} else {
+		// NVIDIA
+		let (line, rest) = split_number(rest.strip_prefix('(')?)?;
+		let rest = rest.strip_prefix(')')?.trim_start();
+		let rest = rest.strip_prefix(':')?;
+
+		Some((line, None, rest.trim_start()))
+	}
+}
+
+/// Split the number at the start of `text` from the rest.
+fn split_number(text: &str) -> Option<(u32, &str)> {
+	let end = text.find(|c: char| !c.is_ascii_digit())
+		.unwrap_or(text.len());
+
+	Some((text[..end].parse().ok()?, &text[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SOURCE: &[u8] = b"void main() {\n\tgl_Position = x;\n}\n";
+
+	fn one(log: &str) -> Diagnostic {
+		let mut diagnostics = parse(Stage::Vertex, log, SOURCE,
+			Severity::Info);
+
+		assert_eq!(diagnostics.len(), 1);
+		diagnostics.remove(0)
+	}
+
+	#[test]
+	fn mesa() {
+		let d = one("0:2(16): error: `x' undeclared\n");
+
+		assert_eq!((d.line, d.column), (Some(2), Some(16)));
+		assert_eq!(d.severity, Severity::Error);
+		assert_eq!(d.message, "`x' undeclared");
+		assert_eq!(d.source.as_deref(),
+			Some("\tgl_Position = x;"));
+		assert_eq!(d.to_string(), "vertex shader 2:16: error: `x' \
+			undeclared\n    gl_Position = x;");
+	}
+
+	#[test]
+	fn nvidia() {
+		let d = one("0(2) : error C1008: undefined variable \"x\"");
+
+		assert_eq!((d.line, d.column), (Some(2), None));
+		assert_eq!(d.severity, Severity::Error);
+		assert_eq!(d.message, "undefined variable \"x\"");
+
+		let d = one("0(3) : warning C7050: \"c\" might be used before \
+			being initialized");
+
+		assert_eq!((d.line, d.severity), (Some(3), Severity::Warning));
+		assert_eq!(d.source.as_deref(), Some("}"));
+	}
+
+	#[test]
+	fn adreno() {
+		let d = one("ERROR: 0:2: 'x' : undeclared identifier ");
+
+		assert_eq!((d.line, d.column), (Some(2), None));
+		assert_eq!(d.severity, Severity::Error);
+		assert_eq!(d.message, "'x' : undeclared identifier");
+	}
+
+	#[test]
+	fn mali() {
+		let d = one("WARNING: 0:1: P0003: Extension 'GL_OES_foo' not \
+			supported");
+
+		assert_eq!((d.line, d.severity), (Some(1), Severity::Warning));
+		assert_eq!(d.message, "P0003: Extension 'GL_OES_foo' not \
+			supported");
+	}
+
+	#[test]
+	fn unknown() {
+		let d = one("ERROR: 1 compilation errors.  No code generated.");
+
+		assert_eq!((d.line, d.column, d.source), (None, None, None));
+		assert_eq!(d.severity, Severity::Error);
+
+		let d = one("Compiled with warnings");
+
+		assert_eq!((d.line, d.severity), (None, Severity::Info));
+		assert_eq!(d.message, "Compiled with warnings");
+	}
+
+	#[test]
+	fn out_of_range_line() {
+		let d = one("0:40(1): warning: unused");
+
+		assert_eq!((d.line, d.source), (Some(40), None));
+	}
+}
@@ -0,0 +1,353 @@
+// Copyright Jeron A. Lau 2018.
+// Dual-licensed under either the MIT License or the Boost Software License,
+// Version 1.0.  (See accompanying file LICENSE_1_0.txt or copy at
+// https://www.boost.org/LICENSE_1_0.txt)
+
+use std::{ error::Error, fmt };
+use { Diagnostic, GlslType };
+
+/// Why an OpenGL context couldn't be created, changed or torn down.  Variants
+/// with an `i32` carry the error code from `eglGetError()` (or `GetLastError()`
+/// on Windows).
+#[derive(Debug)]
+pub enum ContextError {
+	/// The OpenGL / EGL library couldn't be loaded.
+	Library(::dl_api::Error),
+	/// Couldn't connect to a display.
+	NoDisplay(i32),
+	/// `eglInitialize()` failed.
+	Initialize(i32),
+	/// `eglChooseConfig()` failed.
+	ChooseConfig(i32),
+	/// No framebuffer config matched the request.
+	NoConfigs,
+	/// `eglBindAPI()` failed.
+	BindApi(i32),
+	/// Couldn't create the rendering context.
+	CreateContext(i32),
+	/// Couldn't create the window or pbuffer surface.
+	CreateSurface(i32),
+	/// Couldn't make the context current.
+	MakeCurrent(i32),
+	/// Couldn't destroy the window or pbuffer surface.
+	DestroySurface(i32),
+	/// Couldn't destroy the rendering context.
+	DestroyContext(i32),
+	/// `eglTerminate()` failed.
+	Terminate(i32),
+	/// A required extension isn't supported.
+	Unsupported(&'static str),
+	/// A required function couldn't be loaded.
+	MissingSymbol(String),
+	/// The swap interval is outside of what the config supports.
+	InvalidSwapInterval {
+		interval: i32,
+		min: i32,
+		max: i32,
+	},
+	/// Couldn't set the swap interval.
+	SwapInterval(i32),
+	/// Couldn't swap buffers, the surface may have been lost.
+	Swap(i32),
+	/// No OpenGL context is current on this thread.
+	NoCurrent,
+	/// `GL_VERSION` couldn't be parsed.
+	UnknownVersion(String),
+	/// The context was created by another library, which manages its
+	/// surface.
+	NotOwned,
+}
+
+impl fmt::Display for ContextError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use self::ContextError::*;
+
+		let (what, code) = match *self {
+			Library(ref e) => {
+				return write!(f, "Couldn't load library: {}", e)
+			}
+			NoDisplay(code) => ("Couldn't load display", code),
+			Initialize(code) => ("Couldn't initialize", code),
+			ChooseConfig(code) => ("Couldn't choose the config", code),
+			NoConfigs => return write!(f, "No matching configs"),
+			BindApi(code) => ("Couldn't bind API", code),
+			CreateContext(code) => ("Couldn't create context", code),
+			CreateSurface(code) => ("Couldn't create surface", code),
+			MakeCurrent(code) => ("Couldn't make current", code),
+			DestroySurface(code) => ("Couldn't destroy surface", code),
+			DestroyContext(code) => ("Couldn't destroy context", code),
+			Terminate(code) => ("Couldn't terminate", code),
+			Unsupported(extension) => {
+				return write!(f, "{} is not supported", extension)
+			}
+			MissingSymbol(ref name) => {
+				return write!(f, "Couldn't load function \"{}\"",
+					name)
+			}
+			InvalidSwapInterval { interval, min, max } => {
+				return write!(f, "Swap interval {} is not within \
+					{} to {}", interval, min, max)
+			}
+			SwapInterval(code) => ("Couldn't set swap interval", code),
+			Swap(code) => ("Couldn't swap buffers", code),
+			NoCurrent => return write!(f, "No context is current"),
+			UnknownVersion(ref version) => {
+				return write!(f, "Unknown OpenGL version \"{}\"",
+					version)
+			}
+			NotOwned => {
+				return write!(f, "The context is owned by another \
+					library")
+			}
+		};
+
+		write!(f, "{}: {} ({:#X})", what, egl_error_name(code), code)
+	}
+}
+
+impl Error for ContextError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match *self {
+			ContextError::Library(ref e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+/// An error code from `glGetError()`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ErrorCode {
+	InvalidEnum,
+	InvalidValue,
+	InvalidOperation,
+	StackOverflow,
+	StackUnderflow,
+	OutOfMemory,
+	InvalidFramebufferOperation,
+	ContextLost,
+	Unknown(u32),
+}
+
+impl ErrorCode {
+	#[cfg(any(debug_assertions, feature = "checked"))]
+	pub(crate) fn from_raw(code: u32) -> ErrorCode {
+		use self::ErrorCode::*;
+
+		match code {
+			0x0500 => InvalidEnum,
+			0x0501 => InvalidValue,
+			0x0502 => InvalidOperation,
+			0x0503 => StackOverflow,
+			0x0504 => StackUnderflow,
+			0x0505 => OutOfMemory,
+			0x0506 => InvalidFramebufferOperation,
+			0x0507 => ContextLost,
+			code => Unknown(code),
+		}
+	}
+}
+
+impl fmt::Display for ErrorCode {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use self::ErrorCode::*;
+
+		f.write_str(match *self {
+			InvalidEnum => "Invalid enum",
+			InvalidValue => "Invalid value",
+			InvalidOperation => "Invalid operation",
+			StackOverflow => "Stack overflow",
+			StackUnderflow => "Stack underflow",
+			OutOfMemory => "Out of memory",
+			InvalidFramebufferOperation => {
+				"Invalid framebuffer operation"
+			}
+			ContextLost => "Context lost",
+			Unknown(code) => return write!(f, "Unknown ({:#X})", code),
+		})
+	}
+}
+
+/// An OpenGL call that failed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlError {
+	/// What went wrong.
+	pub code: ErrorCode,
+	/// The OpenGL function that failed, like "glBindTexture".
+	pub function: &'static str,
+	/// Where in this crate the function was called.
+	pub file: &'static str,
+	pub line: u32,
+}
+
+impl fmt::Display for GlError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} in {} ({}:{})", self.code, self.function,
+			self.file, self.line)
+	}
+}
+
+impl Error for GlError {}
+
+/// Why a program couldn't be linked, usually because the outputs of the
+/// vertex shader don't match the inputs of the fragment shader.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkError {
+	/// The driver's info log.
+	pub log: String,
+	/// The non-empty lines of the log.
+	pub messages: Vec<String>,
+}
+
+impl LinkError {
+	pub(crate) fn new(log: String) -> LinkError {
+		let messages = log.lines().map(str::trim)
+			.filter(|line| !line.is_empty())
+			.map(str::to_string)
+			.collect();
+
+		LinkError { log, messages }
+	}
+}
+
+impl fmt::Display for LinkError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.messages.is_empty() {
+			return write!(f, "Failed to link");
+		}
+		write!(f, "Failed to link: {}", self.messages.join("; "))
+	}
+}
+
+impl Error for LinkError {}
+
+/// Why a program couldn't be created.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShaderError {
+	/// A shader failed to compile.  Has the diagnostics of all stages.
+	Compile(Vec<Diagnostic>),
+	/// The shaders compiled, but couldn't be linked together.
+	Link(LinkError),
+	/// A shader file couldn't be read or preprocessed.
+	Preprocess(PreprocessError),
+}
+
+impl fmt::Display for ShaderError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ShaderError::Compile(ref diagnostics) => {
+				write!(f, "Failed to compile")?;
+				for diagnostic in diagnostics {
+					write!(f, "\n{}", diagnostic)?;
+				}
+				Ok(())
+			}
+			ShaderError::Link(ref error) => error.fmt(f),
+			ShaderError::Preprocess(ref error) => error.fmt(f),
+		}
+	}
+}
+
+impl Error for ShaderError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match *self {
+			ShaderError::Link(ref e) => Some(e),
+			ShaderError::Preprocess(ref e) => Some(e),
+			ShaderError::Compile(_) => None,
+		}
+	}
+}
+
+impl From<LinkError> for ShaderError {
+	fn from(error: LinkError) -> ShaderError {
+		ShaderError::Link(error)
+	}
+}
+
+impl From<PreprocessError> for ShaderError {
+	fn from(error: PreprocessError) -> ShaderError {
+		ShaderError::Preprocess(error)
+	}
+}
+
+/// A shader couldn't be preprocessed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreprocessError {
+	/// The file with the bad directive.
+	pub file: String,
+	/// The line of the directive, starting at 1.  `None` if the file
+	/// couldn't be read.
+	pub line: Option<u32>,
+	pub message: String,
+}
+
+impl fmt::Display for PreprocessError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.line {
+			Some(line) => write!(f, "{}:{}: {}", self.file, line,
+				self.message),
+			None => write!(f, "{}: {}", self.file, self.message),
+		}
+	}
+}
+
+impl Error for PreprocessError {}
+
+/// A uniform couldn't be set to a value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UniformError {
+	/// The value doesn't match the uniform's GLSL type.
+	Type {
+		/// The uniform's name.
+		name: String,
+		/// The uniform's GLSL type.
+		kind: GlslType,
+		/// The Rust type of the value, like "[f32; 3]".
+		value: &'static str,
+	},
+	/// There are more values than elements in the uniform.
+	Count {
+		/// The uniform's name.
+		name: String,
+		/// The number of elements, 1 if it's not an array.
+		size: i32,
+		/// The number of values.
+		count: usize,
+	},
+}
+
+impl fmt::Display for UniformError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			UniformError::Type { ref name, kind, value } => write!(f,
+				"Can't set uniform `{}` ({}) to a {}", name, kind,
+				value),
+			UniformError::Count { ref name, size, count } => write!(f,
+				"Can't set uniform `{}` to {} values, it has room \
+				for {}", name, count, size),
+		}
+	}
+}
+
+impl Error for UniformError {}
+
+/// Get the name of an `eglGetError()` code.
+fn egl_error_name(code: i32) -> &'static str {
+	match code {
+		0x3000 => "EGL_SUCCESS",
+		0x3001 => "EGL_NOT_INITIALIZED",
+		0x3002 => "EGL_BAD_ACCESS",
+		0x3003 => "EGL_BAD_ALLOC",
+		0x3004 => "EGL_BAD_ATTRIBUTE",
+		0x3005 => "EGL_BAD_CONFIG",
+		0x3006 => "EGL_BAD_CONTEXT",
+		0x3007 => "EGL_BAD_CURRENT_SURFACE",
+		0x3008 => "EGL_BAD_DISPLAY",
+		0x3009 => "EGL_BAD_MATCH",
+		0x300A => "EGL_BAD_NATIVE_PIXMAP",
+		0x300B => "EGL_BAD_NATIVE_WINDOW",
+		0x300C => "EGL_BAD_PARAMETER",
+		0x300D => "EGL_BAD_SURFACE",
+		0x300E => "EGL_CONTEXT_LOST",
+		_ => "Unknown error",
+	}
+}
@@ -8,22 +8,96 @@ extern crate dl_api;
 #[cfg(windows)]
 extern crate winapi;
 
+/// Call an OpenGL function of the context `$o`, then check for errors.
+/// Panics if the context was destroyed.
 macro_rules! gl {
-	($o: expr, $a: stmt) => (
+	($o: expr, $f: ident($($a: expr),* $(,)*)) => (
 		unsafe {
-			let a = { $a };
-			$o.error();
+			let f = {
+				let c = $o.get();
+				c.assert_alive();
+				c.functions.$f
+			};
+			let a = f($($a),*);
+			$o.error(::names::$f, file!(), line!());
 			a
 		}
+	);
+	// An optional function, `None` if it's not available.
+	($o: expr, ?$f: ident($($a: expr),* $(,)*)) => (
+		unsafe {
+			let f = {
+				let c = $o.get();
+				c.assert_alive();
+				c.optional(&c.functions.$f)
+			};
+			match f {
+				Some(f) => {
+					let a = f($($a),*);
+					$o.error(::names::$f, file!(), line!());
+					Some(a)
+				}
+				None => None,
+			}
+		}
+	)
+}
+
+/// Declare the OpenGL functions of a context as `Functions`, with the names
+/// they're loaded by.  `optional` functions are loaded the first time they're
+/// needed.  Also makes a `names` constant for each, for error messages.
+macro_rules! functions {
+	(
+		required {
+			$($(#[$m: meta])* $f: ident: $name: expr
+				=> fn($($a: ty),*) $(-> $r: ty)*;)*
+		}
+		optional {
+			$($o: ident: $oname: expr
+				=> fn($($oa: ty),*) $(-> $or: ty)*;)*
+		}
+	) => (
+		/// The OpenGL functions of a context.
+		struct Functions {
+			$($(#[$m])* $f: unsafe extern "system" fn($($a),*)
+				$(-> $r)*,)*
+			$($o: Lazy<unsafe extern "system" fn($($oa),*)
+				$(-> $or)*>,)*
+		}
+
+		impl Functions {
+			/// Load the required functions with `get`.
+			fn load(get: &mut dyn FnMut(&str) -> *const c_void)
+				-> Result<Functions, ContextError>
+			{
+				Ok(Functions {
+					$($(#[$m])* $f: load(get, $name)?,)*
+					$($o: Lazy::new($oname),)*
+				})
+			}
+		}
+
+		/// The name of the OpenGL function in each field of
+		/// `Functions`.
+		#[allow(non_upper_case_globals, unused)]
+		mod names {
+			$(pub const $f: &str = $name;)*
+			$(pub const $o: &str = $oname;)*
+		}
 	)
 }
 
 use std::os::raw::c_void;
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{ Cell, RefCell };
 
 mod loader;
 mod types;
+mod config;
+mod error;
+mod diagnostic;
+mod reflect;
+mod preprocess;
 
 use types::*;
 
@@ -31,13 +105,28 @@ mod texture;
 mod vertex_data;
 mod uniform_data;
 mod program;
+mod program_cache;
 mod buffer;
+mod std140;
+mod uniform_buffer;
+#[cfg(feature = "mock")]
+pub mod mock;
 
 pub use vertex_data::VertexData;
-pub use uniform_data::UniformData;
+pub use uniform_data::{ Transposed, UniformData, UniformStats,
+	UniformValue };
 pub use program::Program;
+pub use program_cache::ProgramCache;
 pub use buffer::Buffer;
+pub use std140::{ Std140, Std140Writer };
+pub use uniform_buffer::UniformBuffer;
 pub use texture::Texture;
+pub use config::{ Config, ConfigInfo, Platform, Profile, Version };
+pub use error::{ ContextError, ErrorCode, GlError, LinkError,
+	PreprocessError, ShaderError, UniformError };
+pub use diagnostic::{ Diagnostic, Severity, Stage };
+pub use reflect::{ GlslType, Variable };
+pub use preprocess::{ FileInclude, Include, Preprocessor, ShaderSource };
 
 /// Features that can be enabled and disabled.
 #[repr(u32)]
@@ -61,176 +150,751 @@ pub enum Topology {
 	TriangleFan = 0x0006,
 }
 
+/// How finished frames are presented.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PresentMode {
+	/// Swap right away, which may cause tearing.
+	Immediate,
+	/// Wait for the vertical blank, the default.
+	Vsync,
+	/// Wait for the vertical blank, unless the frame is late.  Only on
+	/// Windows, with `WGL_EXT_swap_control_tear`; EGL doesn't support it.
+	Adaptive,
+}
+
+/// Which buffers `OpenGL::clear()` clears, and what to.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ClearFlags {
+	/// RGBA color to clear the color buffer to.
+	pub color: Option<[f32; 4]>,
+	/// Value to clear the depth buffer to, usually 1.0.
+	pub depth: Option<f32>,
+	/// Value to clear the stencil buffer to.
+	pub stencil: Option<i32>,
+}
+
+/// What to do when an OpenGL call fails.  Errors are only checked in debug
+/// builds, or with the `checked` feature.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ErrorPolicy {
+	/// Panic with the error, the default.
+	Panic,
+	/// Print the error to stderr.
+	Log,
+	/// Queue the error, to be drained with `OpenGL::errors()`.
+	Collect,
+	/// Don't check for errors at all.  The driver keeps them flagged, so
+	/// they're reported by the next check under another policy.
+	Ignore,
+}
+
+/// Optional features, which need more than OpenGL ES 2.0.  Check for them
+/// with `OpenGL::has()`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Capability {
+	/// Vertex array objects (OpenGL ES 3.0, OpenGL 3.0).
+	VertexArray,
+	/// Instanced drawing (OpenGL ES 3.0, OpenGL 3.3).
+	Instancing,
+	/// Uniform buffer objects (OpenGL ES 3.0, OpenGL 3.1).
+	UniformBuffer,
+	/// Getting and loading program binaries (OpenGL ES 3.0, OpenGL 4.1,
+	/// or OpenGL ES 2.0 with `GL_OES_get_program_binary`).
+	ProgramBinary,
+	/// Unsigned integer uniforms (OpenGL ES 3.0, OpenGL 3.0).
+	UnsignedUniform,
+}
+
+/// A kind of object whose binding is cached, to skip redundant binds.
+#[derive(Copy, Clone)]
+pub(crate) enum Binding {
+	Program,
+	Texture,
+	Buffer,
+}
+
 /// The OpenGL context.
 #[derive(Clone)] pub struct OpenGL(Rc<RefCell<OpenGLContext>>);
 
 /// The OpenGL builder.
 pub struct OpenGLBuilder {
-	lib: loader::Lib,
+	// Declared before `lib`, so it's torn down before EGL is unloaded.
 	display: loader::Display,
+	lib: loader::Lib,
 }
 
 impl OpenGLBuilder {
 	/// Begin the building.
-	pub fn new() -> Option<(OpenGLBuilder, i32)> {
-		if let Ok(lib) = loader::Lib::new() {
-			let (mut display, visual_id) = lib.init();
-
-			Some((OpenGLBuilder {
-				lib,
-				display,
-			}, visual_id))
-		} else {
-			None
-		}
+	pub fn new() -> Result<(OpenGLBuilder, i32), ContextError> {
+		Self::with_config(&Config::default())
+	}
+
+	/// Begin the building, with a framebuffer config.
+	pub fn with_config(config: &Config)
+		-> Result<(OpenGLBuilder, i32), ContextError>
+	{
+		let lib = loader::Lib::new()?;
+		let (display, visual_id) = lib.init(config, false)?;
+
+		Ok((OpenGLBuilder {
+			lib,
+			display,
+		}, visual_id))
+	}
+
+	/// Begin the building of a context that doesn't need a window.  The
+	/// Mesa surfaceless platform is used if available, so no window system
+	/// needs to be running.
+	#[cfg(not(windows))]
+	pub fn headless() -> Result<OpenGLBuilder, ContextError> {
+		Self::headless_with_config(&Config::default())
+	}
+
+	/// Begin the building of a context that doesn't need a window, with a
+	/// framebuffer config.
+	#[cfg(not(windows))]
+	pub fn headless_with_config(config: &Config)
+		-> Result<OpenGLBuilder, ContextError>
+	{
+		let lib = loader::Lib::new()?;
+		let (display, _) = lib.init(config, true)?;
+
+		Ok(OpenGLBuilder {
+			lib,
+			display,
+		})
+	}
+
+	/// Get the EGL devices (GPUs), for headless rendering with
+	/// `Platform::Device`.  Requires `EGL_EXT_device_enumeration`.  The
+	/// handles stay valid for the rest of the process, since EGL is never
+	/// unloaded once it's been loaded.
+	#[cfg(not(windows))]
+	pub fn devices() -> Result<Vec<*mut c_void>, ContextError> {
+		loader::Lib::new()?.devices()
+	}
+
+	/// Get all of the framebuffer configs that matched the request.
+	pub fn configs(&self) -> &[ConfigInfo] {
+		&self.display.configs
+	}
+
+	/// Get the framebuffer config that was chosen.
+	pub fn config(&self) -> &ConfigInfo {
+		&self.display.configs[self.display.chosen]
 	}
 
 	/// Complete the building
-	pub fn to_opengl(mut self, window: EGLNativeWindowType) -> OpenGL {
-		self.lib.init2(&mut self.display, window);
-
-		OpenGL(Rc::new(RefCell::new(OpenGLContext {
-			// FFI OpenGL Functions.
-			clear: self.lib.load(b"glClear\0"),
-			clear_color: self.lib.load(b"glClearColor\0"),
-			disable: self.lib.load(b"glDisable\0"),
-			enable: self.lib.load(b"glEnable\0"),
-			#[cfg(debug_assertions)]
-			get_error: self.lib.load(b"glGetError\0"),
-			blend_func_separate:
-				self.lib.load(b"glBlendFuncSeparate\0"),
-			create_shader: self.lib.load(b"glCreateShader\0"),
-			shader_source: self.lib.load(b"glShaderSource\0"),
-			compile_shader: self.lib.load(b"glCompileShader\0"),
-			create_program: self.lib.load(b"glCreateProgram\0"),
-			attach_shader: self.lib.load(b"glAttachShader\0"),
-			link_program: self.lib.load(b"glLinkProgram\0"),
-			uniform: self.lib.load(b"glGetUniformLocation\0"),
-			gen_buffers: self.lib.load(b"glGenBuffers\0"),
-			bind_buffer: self.lib.load(b"glBindBuffer\0"),
-			buffer_data: self.lib.load(b"glBufferData\0"),
-			vdata: self.lib.load(b"glGetAttribLocation\0"),
-			#[cfg(debug_assertions)]
-			get_shader: self.lib.load(b"glGetShaderiv\0"),
-			#[cfg(debug_assertions)]
-			info_log: self.lib.load(b"glGetShaderInfoLog\0"),
-			draw_arrays: self.lib.load(b"glDrawArrays\0"),
-			use_program: self.lib.load(b"glUseProgram\0"),
-			uniform_mat4: self.lib.load(b"glUniformMatrix4fv\0"),
-			uniform_int1: self.lib.load(b"glUniform1i\0"),
-			uniform_vec1: self.lib.load(b"glUniform1f\0"),
-			uniform_vec2: self.lib.load(b"glUniform2f\0"),
-			uniform_vec3: self.lib.load(b"glUniform3f\0"),
-			uniform_vec4: self.lib.load(b"glUniform4f\0"),
-			bind_texture: self.lib.load(b"glBindTexture\0"),
-			vertex_attrib: self.lib.load(b"glVertexAttribPointer\0"),
-			gen_textures: self.lib.load(b"glGenTextures\0"),
-			tex_params: self.lib.load(b"glTexParameteri\0"),
-			tex_image: self.lib.load(b"glTexImage2D\0"),
-			tex_subimage: self.lib.load(b"glTexSubImage2D\0"),
-			enable_vdata: self.lib.load(b"glEnableVertexAttribArray\0"),
-			viewport: self.lib.load(b"glViewport\0"),
-			gen_mipmap: self.lib.load(b"glGenerateMipmap\0"),
-			detach_shader: self.lib.load(b"glDetachShader\0"),
-			delete_program: self.lib.load(b"glDeleteProgram\0"),
-			delete_buffer: self.lib.load(b"glDeleteBuffers\0"),
-			delete_texture: self.lib.load(b"glDeleteTextures\0"),
-			stencil_op: self.lib.load(b"glStencilOp\0"),
-			stencil_func: self.lib.load(b"glStencilFunc\0"),
-			// Other
-			display: self.display,
-			lib: self.lib,
-		})))
+	pub fn to_opengl(mut self, window: EGLNativeWindowType)
+		-> Result<OpenGL, ContextError>
+	{
+		self.lib.init2(&mut self.display, window)?;
+		self.finish()
+	}
+
+	/// Complete the building, rendering to an offscreen `w`×`h` pbuffer.
+	/// Should be started with `headless()`.
+	#[cfg(not(windows))]
+	pub fn to_opengl_pbuffer(mut self, w: u16, h: u16)
+		-> Result<OpenGL, ContextError>
+	{
+		self.lib.init2_pbuffer(&mut self.display, w, h)?;
+		self.finish()
+	}
+
+	/// Complete the building without any surface (requires
+	/// `EGL_KHR_surfaceless_context`).  There's no default framebuffer, so
+	/// don't call `update()`.
+	#[cfg(not(windows))]
+	pub fn to_opengl_surfaceless(mut self) -> Result<OpenGL, ContextError> {
+		self.lib.init2_surfaceless(&mut self.display)?;
+		self.finish()
+	}
+
+	/// Load the OpenGL functions for the now current context.
+	fn finish(self) -> Result<OpenGL, ContextError> {
+		let OpenGLBuilder { display, lib } = self;
+		let version = display.version;
+		let context = OpenGLContext::new(Some(version),
+			Source::Owned(Box::new(Owned { display, lib })))?;
+
+		Ok(OpenGL(Rc::new(RefCell::new(context))))
+	}
+}
+
+/// The display and library of a context created by this crate.
+struct Owned {
+	// Declared before `lib`, so it's torn down before EGL is unloaded.
+	display: loader::Display,
+	lib: loader::Lib,
+}
+
+/// Where the OpenGL functions are loaded from.
+enum Source {
+	/// A context created by this crate.
+	Owned(Box<Owned>),
+	/// A context created by another library, and its `get_proc_address`.
+	Foreign(RefCell<GetProcAddress>),
+}
+
+type GetProcAddress = Box<dyn FnMut(&str) -> *const c_void>;
+
+impl Source {
+	/// Get the address of an OpenGL function, null if it's not available.
+	fn proc_address(&self, name: &str) -> *const c_void {
+		match *self {
+			Source::Owned(ref owned) => owned.lib.proc_address(name),
+			Source::Foreign(ref get) => (get.borrow_mut())(name),
+		}
+	}
+}
+
+/// An optional OpenGL function, loaded the first time it's needed.
+struct Lazy<T: Copy> {
+	name: &'static str,
+	// `None` until loaded, then `Some(None)` if it's not available.
+	function: Cell<Option<Option<T>>>,
+}
+
+impl<T: Copy> Lazy<T> {
+	fn new(name: &'static str) -> Lazy<T> {
+		Lazy { name, function: Cell::new(None) }
+	}
+
+	/// Get the function, loading it from `source` if it isn't yet.
+	fn get(&self, source: &Source) -> Option<T> {
+		if let Some(function) = self.function.get() {
+			return function;
+		}
+
+		let function = load(&mut |name| source.proc_address(name),
+			self.name).ok();
+
+		self.function.set(Some(function));
+		function
+	}
+}
+
+/// Load an OpenGL function with `get`.
+fn load<T>(get: &mut dyn FnMut(&str) -> *const c_void, name: &str)
+	-> Result<T, ContextError>
+{
+	let fn_ptr = get(name);
+
+	if fn_ptr.is_null() {
+		Err(ContextError::MissingSymbol(name.to_string()))
+	} else {
+		Ok(unsafe { std::mem::transmute_copy(&fn_ptr) })
+	}
+}
+
+impl OpenGLContext {
+	/// Load the required OpenGL functions for the current context from
+	/// `source`.  The version is read from `GL_VERSION`, falling back to
+	/// `requested` if it can't be parsed, and the profile from
+	/// `GL_CONTEXT_PROFILE_MASK`, falling back to the requested one.
+	fn new(requested: Option<Version>, source: Source)
+		-> Result<OpenGLContext, ContextError>
+	{
+		let functions = Functions::load(&mut |name: &str| {
+			source.proc_address(name)
+		})?;
+		let string = unsafe { (functions.get_string)(GL_VERSION) };
+
+		if string.is_null() {
+			return Err(ContextError::NoCurrent);
+		}
+
+		let string = unsafe {
+			std::ffi::CStr::from_ptr(string as *const _)
+		}.to_string_lossy();
+		let requested_profile = match requested {
+			Some(Version::Gl(_, _, profile)) => profile,
+			_ => Profile::Compatibility,
+		};
+		let mut version = Version::parse(&string, requested_profile)
+			.or(requested)
+			.ok_or_else(|| {
+				ContextError::UnknownVersion(string.into_owned())
+			})?;
+
+		// NVIDIA doesn't say which profile it is in `GL_VERSION`.
+		if let Version::Gl(major, minor, ref mut profile) = version {
+			if (major, minor) >= (3, 2) {
+				let mut mask = 0;
+
+				unsafe {
+					(functions.get_integer)(
+						GL_CONTEXT_PROFILE_MASK,
+						&mut mask);
+				}
+				// Zero if the query failed, so keep the
+				// requested profile.
+				if mask & 0x1 != 0 { // CORE_PROFILE_BIT
+					*profile = Profile::Core;
+				} else if mask & 0x2 != 0 {
+					*profile = Profile::Compatibility;
+				}
+			}
+		}
+
+		let context = OpenGLContext {
+			functions,
+			source,
+			version,
+			bound_program: Cell::new(0),
+			bound_texture: Cell::new(0),
+			bound_buffer: Cell::new(u32::MAX),
+			error_policy: Cell::new(ErrorPolicy::Panic),
+			errors: RefCell::new(Vec::new()),
+			uniform_stats: Cell::new(UniformStats::default()),
+		};
+
+		// Core profiles can't draw without a vertex array object, so
+		// bind one for the life of the context.
+		if let Version::Gl(major, minor, Profile::Core) = version {
+			if (major, minor) >= (3, 2) {
+				let missing = |name: &str| {
+					ContextError::MissingSymbol(name.to_string())
+				};
+				let gen_vertex_arrays = context.functions
+					.gen_vertex_arrays
+					.get(&context.source)
+					.ok_or_else(|| missing("glGenVertexArrays"))?;
+				let bind_vertex_array = context.functions
+					.bind_vertex_array
+					.get(&context.source)
+					.ok_or_else(|| missing("glBindVertexArray"))?;
+				let mut vertex_array = 0;
+
+				unsafe {
+					gen_vertex_arrays(1, &mut vertex_array);
+					bind_vertex_array(vertex_array);
+				}
+			}
+		}
+
+		Ok(context)
+	}
+
+	/// Get an optional function, `None` if it's not available.
+	fn optional<T: Copy>(&self, function: &Lazy<T>) -> Option<T> {
+		function.get(&self.source)
+	}
+
+	/// If `OpenGL::destroy()` has been called on this context.
+	fn is_destroyed(&self) -> bool {
+		match self.source {
+			Source::Owned(ref owned) => owned.display.is_terminated(),
+			Source::Foreign(_) => false,
+		}
+	}
+
+	/// Panic if the context was destroyed, since calling the driver after
+	/// the display is terminated is undefined behavior.
+	fn assert_alive(&self) {
+		if self.is_destroyed() {
+			panic!("OpenGL context used after destroy()");
+		}
+	}
+
+	/// Get the owned display and library, an error if the context was
+	/// created by another library.
+	fn owned(&mut self) -> Result<&mut Owned, ContextError> {
+		match self.source {
+			Source::Owned(ref mut owned) => Ok(owned),
+			Source::Foreign(_) => Err(ContextError::NotOwned),
+		}
 	}
 }
 
 /// The OpenGL context.
 struct OpenGLContext {
-	#[allow(unused)] // is used at drop.
-	lib: loader::Lib,
-	display: loader::Display,
-	clear: unsafe extern "system" fn(GLbitfield) -> (),
-	clear_color: unsafe extern "system" fn(GLfloat, GLfloat, GLfloat,
-		GLfloat) -> (),
-	disable: unsafe extern "system" fn(GLenum) -> (),
-	enable: unsafe extern "system" fn(GLenum) -> (),
-	#[cfg(debug_assertions)] get_error: unsafe extern "system" fn() -> GLenum,
-	blend_func_separate: unsafe extern "system" fn(GLenum, GLenum, GLenum,
-		GLenum) -> (),
-	create_shader: unsafe extern "system" fn(GLenum) -> GLuint,
-	shader_source: unsafe extern "system" fn(GLuint, GLsizei,
-		*const *const GLchar, *const GLint) -> (),
-	compile_shader: unsafe extern "system" fn(GLuint) -> (),
-	create_program: unsafe extern "system" fn() -> GLuint,
-	attach_shader: unsafe extern "system" fn(GLuint, GLuint) -> (),
-	link_program: unsafe extern "system" fn(GLuint) -> (),
-	uniform: unsafe extern "system" fn(GLuint, *const GLchar) -> GLint,
-	gen_buffers: unsafe extern "system" fn(GLsizei, *mut GLuint) -> (),
-	bind_buffer: unsafe extern "system" fn(GLenum, GLuint) -> (),
-	buffer_data: unsafe extern "system" fn(GLenum, GLsizeiptr,
-		*const c_void, GLenum) -> (),
-	vdata: unsafe extern "system" fn(GLuint, *const GLchar) -> GLint,
-	#[cfg(debug_assertions)]
-	get_shader: unsafe extern "system" fn(GLuint, GLenum, *mut GLint) -> (),
-	#[cfg(debug_assertions)]
-	info_log: unsafe extern "system" fn(GLuint, GLsizei, *mut GLsizei,
-		*mut GLchar) -> (),
-	draw_arrays: unsafe extern "system" fn(GLenum, GLint, GLsizei) -> (),
-	use_program: unsafe extern "system" fn(GLuint) -> (),
-	uniform_mat4: unsafe extern "system" fn(GLint, GLsizei, GLboolean,
-		*const GLfloat) -> (),
-	uniform_int1: unsafe extern "system" fn(GLint, GLint) -> (),
-	uniform_vec1: unsafe extern "system" fn(GLint, GLfloat) -> (),
-	uniform_vec2: unsafe extern "system" fn(GLint, GLfloat, GLfloat) -> (),
-	uniform_vec3: unsafe extern "system" fn(GLint, GLfloat, GLfloat, GLfloat)
-		-> (),
-	uniform_vec4: unsafe extern "system" fn(GLint, GLfloat, GLfloat, GLfloat,
-		GLfloat) -> (),
-	bind_texture: unsafe extern "system" fn(GLenum, GLuint) -> (),
-	vertex_attrib: unsafe extern "system" fn(GLuint, GLint, GLenum,
-		GLboolean, GLsizei, *const c_void) -> (),
-	gen_textures: unsafe extern "system" fn(GLsizei, *mut GLuint) -> (),
-	tex_params: unsafe extern "system" fn(GLenum, GLenum, GLint) -> (),
-	tex_image: unsafe extern "system" fn(GLenum, GLint, GLint, GLsizei,
-		GLsizei, GLint, GLenum, GLenum, *const c_void) -> (),
-	tex_subimage: unsafe extern "system" fn(GLenum, GLint, GLint, GLint, GLsizei,
-		GLsizei, GLenum, GLenum, *const c_void) -> (),
-	enable_vdata: unsafe extern "system" fn(GLuint) -> (),
-	viewport: unsafe extern "system" fn(GLint, GLint, GLsizei, GLsizei) -> (),
-	gen_mipmap: unsafe extern "system" fn(GLenum) -> (),
-	detach_shader: unsafe extern "system" fn(GLuint, GLuint) -> (),
-	delete_program: unsafe extern "system" fn(GLuint) -> (),
-	delete_buffer: unsafe extern "system" fn(GLsizei, *const GLuint) -> (),
-	delete_texture: unsafe extern "system" fn(GLsizei, *const GLuint) -> (),
-	stencil_op: unsafe extern "system" fn(GLenum, GLenum, GLenum) -> (),
-	stencil_func: unsafe extern "system" fn(GLenum, GLint, GLuint) -> (),
+	source: Source,
+	version: Version,
+	// The currently bound objects, to skip redundant binds.
+	bound_program: Cell<GLuint>, // 0 is always invalid program.
+	bound_texture: Cell<GLuint>, // 0 is always invalid texture.
+	bound_buffer: Cell<GLuint>, // MAX is no current buffer
+	error_policy: Cell<ErrorPolicy>,
+	// Errors queued by `ErrorPolicy::Collect`.
+	errors: RefCell<Vec<GlError>>,
+	uniform_stats: Cell<UniformStats>,
+	functions: Functions,
+}
+
+functions! {
+	required {
+		get_string: "glGetString" => fn(GLenum) -> *const GLubyte;
+		get_integer: "glGetIntegerv" => fn(GLenum, *mut GLint);
+		clear: "glClear" => fn(GLbitfield);
+		clear_color: "glClearColor" => fn(GLfloat, GLfloat, GLfloat,
+			GLfloat);
+		clear_stencil: "glClearStencil" => fn(GLint);
+		disable: "glDisable" => fn(GLenum);
+		enable: "glEnable" => fn(GLenum);
+		#[cfg(any(debug_assertions, feature = "checked"))]
+		get_error: "glGetError" => fn() -> GLenum;
+		blend_func_separate: "glBlendFuncSeparate" => fn(GLenum, GLenum,
+			GLenum, GLenum);
+		create_shader: "glCreateShader" => fn(GLenum) -> GLuint;
+		shader_source: "glShaderSource" => fn(GLuint, GLsizei,
+			*const *const GLchar, *const GLint);
+		compile_shader: "glCompileShader" => fn(GLuint);
+		create_program: "glCreateProgram" => fn() -> GLuint;
+		attach_shader: "glAttachShader" => fn(GLuint, GLuint);
+		link_program: "glLinkProgram" => fn(GLuint);
+		uniform: "glGetUniformLocation" => fn(GLuint,
+			*const GLchar) -> GLint;
+		gen_buffers: "glGenBuffers" => fn(GLsizei, *mut GLuint);
+		bind_buffer: "glBindBuffer" => fn(GLenum, GLuint);
+		buffer_data: "glBufferData" => fn(GLenum, GLsizeiptr,
+			*const c_void, GLenum);
+		vdata: "glGetAttribLocation" => fn(GLuint,
+			*const GLchar) -> GLint;
+		get_shader: "glGetShaderiv" => fn(GLuint, GLenum, *mut GLint);
+		info_log: "glGetShaderInfoLog" => fn(GLuint, GLsizei,
+			*mut GLsizei, *mut GLchar);
+		draw_arrays: "glDrawArrays" => fn(GLenum, GLint, GLsizei);
+		use_program: "glUseProgram" => fn(GLuint);
+		uniform_mat4: "glUniformMatrix4fv" => fn(GLint, GLsizei,
+			GLboolean, *const GLfloat);
+		uniform_int1: "glUniform1i" => fn(GLint, GLint);
+		uniform_vec1: "glUniform1f" => fn(GLint, GLfloat);
+		uniform_vec2: "glUniform2f" => fn(GLint, GLfloat, GLfloat);
+		uniform_vec3: "glUniform3f" => fn(GLint, GLfloat, GLfloat,
+			GLfloat);
+		uniform_vec4: "glUniform4f" => fn(GLint, GLfloat, GLfloat,
+			GLfloat, GLfloat);
+		uniform_int2: "glUniform2i" => fn(GLint, GLint, GLint);
+		uniform_int3: "glUniform3i" => fn(GLint, GLint, GLint, GLint);
+		uniform_int4: "glUniform4i" => fn(GLint, GLint, GLint, GLint,
+			GLint);
+		uniform_mat2: "glUniformMatrix2fv" => fn(GLint, GLsizei,
+			GLboolean, *const GLfloat);
+		uniform_mat3: "glUniformMatrix3fv" => fn(GLint, GLsizei,
+			GLboolean, *const GLfloat);
+		uniform_vecs1: "glUniform1fv" => fn(GLint, GLsizei,
+			*const GLfloat);
+		uniform_vecs2: "glUniform2fv" => fn(GLint, GLsizei,
+			*const GLfloat);
+		uniform_vecs3: "glUniform3fv" => fn(GLint, GLsizei,
+			*const GLfloat);
+		uniform_vecs4: "glUniform4fv" => fn(GLint, GLsizei,
+			*const GLfloat);
+		uniform_ints1: "glUniform1iv" => fn(GLint, GLsizei,
+			*const GLint);
+		uniform_ints2: "glUniform2iv" => fn(GLint, GLsizei,
+			*const GLint);
+		uniform_ints3: "glUniform3iv" => fn(GLint, GLsizei,
+			*const GLint);
+		uniform_ints4: "glUniform4iv" => fn(GLint, GLsizei,
+			*const GLint);
+		bind_texture: "glBindTexture" => fn(GLenum, GLuint);
+		vertex_attrib: "glVertexAttribPointer" => fn(GLuint, GLint,
+			GLenum, GLboolean, GLsizei, *const c_void);
+		gen_textures: "glGenTextures" => fn(GLsizei, *mut GLuint);
+		tex_params: "glTexParameteri" => fn(GLenum, GLenum, GLint);
+		tex_image: "glTexImage2D" => fn(GLenum, GLint, GLint, GLsizei,
+			GLsizei, GLint, GLenum, GLenum, *const c_void);
+		tex_subimage: "glTexSubImage2D" => fn(GLenum, GLint, GLint,
+			GLint, GLsizei, GLsizei, GLenum, GLenum,
+			*const c_void);
+		enable_vdata: "glEnableVertexAttribArray" => fn(GLuint);
+		viewport: "glViewport" => fn(GLint, GLint, GLsizei, GLsizei);
+		gen_mipmap: "glGenerateMipmap" => fn(GLenum);
+		detach_shader: "glDetachShader" => fn(GLuint, GLuint);
+		delete_program: "glDeleteProgram" => fn(GLuint);
+		delete_shader: "glDeleteShader" => fn(GLuint);
+		get_program: "glGetProgramiv" => fn(GLuint, GLenum, *mut GLint);
+		program_info_log: "glGetProgramInfoLog" => fn(GLuint, GLsizei,
+			*mut GLsizei, *mut GLchar);
+		active_attrib: "glGetActiveAttrib" => fn(GLuint, GLuint,
+			GLsizei, *mut GLsizei, *mut GLint, *mut GLenum,
+			*mut GLchar);
+		active_uniform: "glGetActiveUniform" => fn(GLuint, GLuint,
+			GLsizei, *mut GLsizei, *mut GLint, *mut GLenum,
+			*mut GLchar);
+		delete_buffer: "glDeleteBuffers" => fn(GLsizei, *const GLuint);
+		delete_texture: "glDeleteTextures" => fn(GLsizei,
+			*const GLuint);
+		stencil_op: "glStencilOp" => fn(GLenum, GLenum, GLenum);
+		stencil_func: "glStencilFunc" => fn(GLenum, GLint, GLuint);
+		bind_attrib: "glBindAttribLocation" => fn(GLuint, GLuint,
+			*const GLchar);
+	}
+	optional {
+		// Desktop OpenGL before 4.1 may only have `glClearDepth()`.
+		clear_depth: "glClearDepthf" => fn(GLfloat);
+		clear_depth_double: "glClearDepth" => fn(GLdouble);
+
+		// See `Capability`.
+		gen_vertex_arrays: "glGenVertexArrays" => fn(GLsizei,
+			*mut GLuint);
+		bind_vertex_array: "glBindVertexArray" => fn(GLuint);
+		delete_vertex_arrays: "glDeleteVertexArrays" => fn(GLsizei,
+			*const GLuint);
+		draw_arrays_instanced: "glDrawArraysInstanced" => fn(GLenum,
+			GLint, GLsizei, GLsizei);
+		vertex_attrib_divisor: "glVertexAttribDivisor" => fn(GLuint,
+			GLuint);
+		get_uniform_block_index: "glGetUniformBlockIndex" => fn(GLuint,
+			*const GLchar) -> GLuint;
+		uniform_block_binding: "glUniformBlockBinding" => fn(GLuint,
+			GLuint, GLuint);
+		bind_buffer_base: "glBindBufferBase" => fn(GLenum, GLuint,
+			GLuint);
+		get_program_binary: "glGetProgramBinary" => fn(GLuint, GLsizei,
+			*mut GLsizei, *mut GLenum, *mut c_void);
+		program_binary: "glProgramBinary" => fn(GLuint, GLenum,
+			*const c_void, GLsizei);
+		program_parameter: "glProgramParameteri" => fn(GLuint, GLenum,
+			GLint);
+		// `GL_OES_get_program_binary`, for OpenGL ES 2.0.
+		get_program_binary_oes: "glGetProgramBinaryOES" => fn(GLuint,
+			GLsizei, *mut GLsizei, *mut GLenum, *mut c_void);
+		program_binary_oes: "glProgramBinaryOES" => fn(GLuint, GLenum,
+			*const c_void, GLsizei);
+		uniform_uint1: "glUniform1ui" => fn(GLint, GLuint);
+		uniform_uint2: "glUniform2ui" => fn(GLint, GLuint, GLuint);
+		uniform_uint3: "glUniform3ui" => fn(GLint, GLuint, GLuint,
+			GLuint);
+		uniform_uint4: "glUniform4ui" => fn(GLint, GLuint, GLuint,
+			GLuint, GLuint);
+		uniform_uints1: "glUniform1uiv" => fn(GLint, GLsizei,
+			*const GLuint);
+		uniform_uints2: "glUniform2uiv" => fn(GLint, GLsizei,
+			*const GLuint);
+		uniform_uints3: "glUniform3uiv" => fn(GLint, GLsizei,
+			*const GLuint);
+		uniform_uints4: "glUniform4uiv" => fn(GLint, GLsizei,
+			*const GLuint);
+	}
 }
 
 impl OpenGL {
+	/// Use a context created by another library (SDL, glfw, winit, …).
+	/// The context must be current on this thread, and `get_proc_address`
+	/// should return the address of an OpenGL function (null if missing).
+	///
+	/// The other library keeps ownership: swap buffers and manage the
+	/// window with it, `present()` does nothing and `set_window()`,
+	/// `release_window()` and `set_swap_interval()` return
+	/// `ContextError::NotOwned`.
+	///
+	/// If other code binds buffers, textures or programs on the context,
+	/// call `invalidate_bindings()` before using this crate again.
+	pub fn from_loader<F>(get_proc_address: F)
+		-> Result<OpenGL, ContextError>
+		where F: FnMut(&str) -> *const c_void + 'static
+	{
+		let context = OpenGLContext::new(None, Source::Foreign(
+			RefCell::new(Box::new(get_proc_address))))?;
+
+		Ok(OpenGL(Rc::new(RefCell::new(context))))
+	}
+
+	/// Check if an optional feature is supported.  Its functions are
+	/// loaded the first time this is called.
+	pub fn has(&self, capability: Capability) -> bool {
+		let context = self.get();
+		let c = &*context;
+		let f = &c.functions;
+
+		match capability {
+			Capability::VertexArray => {
+				c.version.at_least((3, 0), (3, 0))
+				&& c.optional(&f.gen_vertex_arrays).is_some()
+				&& c.optional(&f.bind_vertex_array).is_some()
+				&& c.optional(&f.delete_vertex_arrays).is_some()
+			}
+			Capability::Instancing => {
+				c.version.at_least((3, 0), (3, 3))
+				&& c.optional(&f.draw_arrays_instanced).is_some()
+				&& c.optional(&f.vertex_attrib_divisor).is_some()
+			}
+			Capability::UniformBuffer => {
+				c.version.at_least((3, 0), (3, 1))
+				&& c.optional(&f.get_uniform_block_index).is_some()
+				&& c.optional(&f.uniform_block_binding).is_some()
+				&& c.optional(&f.bind_buffer_base).is_some()
+			}
+			Capability::ProgramBinary if self.core_binary() => {
+				c.optional(&f.get_program_binary).is_some()
+				&& c.optional(&f.program_binary).is_some()
+				&& c.optional(&f.program_parameter).is_some()
+			}
+			Capability::ProgramBinary => {
+				matches!(c.version, Version::GlEs(..))
+				&& self.has_extension("GL_OES_get_program_binary")
+				&& c.optional(&f.get_program_binary_oes).is_some()
+				&& c.optional(&f.program_binary_oes).is_some()
+			}
+			Capability::UnsignedUniform => {
+				c.version.at_least((3, 0), (3, 0))
+				&& c.optional(&f.uniform_uint1).is_some()
+				&& c.optional(&f.uniform_uint2).is_some()
+				&& c.optional(&f.uniform_uint3).is_some()
+				&& c.optional(&f.uniform_uint4).is_some()
+				&& c.optional(&f.uniform_uints1).is_some()
+				&& c.optional(&f.uniform_uints2).is_some()
+				&& c.optional(&f.uniform_uints3).is_some()
+				&& c.optional(&f.uniform_uints4).is_some()
+			}
+		}
+	}
+
 	/// Set the color for `clear`.
 	pub fn color(&self, r: f32, g: f32, b: f32) {
-		gl!(self, (self.get().clear_color)(r, g, b, 1.0));
+		gl!(self, clear_color(r, g, b, 1.0));
 	}
 
-	/// Update the screen
+	/// Update the screen: `present()`, then clear color & depth.  Panics
+	/// if presenting fails.
 	pub fn update(&self) {
-		// Swap Display
-		self.get().display.swap(
-			#[cfg(not(target_os = "windows"))]
-			&self.get().lib
-		);
+		if let Err(e) = self.present() {
+			panic!("Swapping Failed: {}", e);
+		}
 		// Clear Color & Depth
-		gl!(self, (self.get().clear)(0x00000100 | 0x00004000));
+		gl!(self, clear(0x00000100 | 0x00004000));
+	}
+
+	/// Show the rendered frame by swapping buffers.  Does nothing if
+	/// there's no window or pbuffer.
+	pub fn present(&self) -> Result<(), ContextError> {
+		match self.get().source {
+			Source::Owned(ref owned) => owned.display.swap(
+				#[cfg(not(target_os = "windows"))]
+				&owned.lib
+			),
+			Source::Foreign(_) => Ok(()),
+		}
+	}
+
+	/// Clear the buffers that are `Some` to their values.
+	pub fn clear(&self, flags: ClearFlags) {
+		let mut mask = 0;
+
+		if let Some([r, g, b, a]) = flags.color {
+			gl!(self, clear_color(r, g, b, a));
+			mask |= 0x00004000; // GL_COLOR_BUFFER_BIT
+		}
+		if let Some(depth) = flags.depth {
+			if gl!(self, ?clear_depth(depth)).is_none() {
+				gl!(self, ?clear_depth_double(depth.into()))
+					.expect("No glClearDepthf or glClearDepth");
+			}
+			mask |= 0x00000100; // GL_DEPTH_BUFFER_BIT
+		}
+		if let Some(stencil) = flags.stencil {
+			gl!(self, clear_stencil(stencil));
+			mask |= 0x00000400; // GL_STENCIL_BUFFER_BIT
+		}
+
+		if mask != 0 {
+			gl!(self, clear(mask));
+		}
+	}
+
+	/// Get the version of the context, read from `GL_VERSION`.  This may be
+	/// newer than the version in `Config::versions` it was created for.
+	pub fn version(&self) -> Version {
+		self.get().version
+	}
+
+	/// Render to a new window, replacing the old window's surface.  The
+	/// context and all objects created from it are kept.
+	pub fn set_window(&self, window: EGLNativeWindowType)
+		-> Result<(), ContextError>
+	{
+		let mut context = self.0.borrow_mut();
+		let owned = context.owned()?;
+
+		owned.lib.set_window(&mut owned.display, window)
+	}
+
+	/// Stop rendering to the window, for when the native window is lost
+	/// (like when an Android app is paused).  The context and all objects
+	/// created from it are kept, call `set_window()` before drawing again.
+	pub fn release_window(&self) -> Result<(), ContextError> {
+		let mut context = self.0.borrow_mut();
+		let owned = context.owned()?;
+
+		owned.lib.release_window(&mut owned.display)
+	}
+
+	/// Set how many vertical blanks to wait for before swapping, 0 to not
+	/// wait and negative for adaptive vsync (Windows only, see
+	/// `PresentMode::Adaptive`).
+	pub fn set_swap_interval(&self, interval: i32)
+		-> Result<(), ContextError>
+	{
+		let mut context = self.0.borrow_mut();
+		let owned = context.owned()?;
+
+		owned.lib.set_swap_interval(&mut owned.display, interval)
+	}
+
+	/// Set how finished frames are presented.
+	pub fn set_present_mode(&self, mode: PresentMode)
+		-> Result<(), ContextError>
+	{
+		self.set_swap_interval(match mode {
+			PresentMode::Immediate => 0,
+			PresentMode::Vsync => 1,
+			PresentMode::Adaptive => -1,
+		})
+	}
+
+	/// Destroy the context now, instead of when the last reference is
+	/// dropped.  Using a clone of this `OpenGL` or an object created from
+	/// it afterwards panics; dropping them is fine.
+	pub fn destroy(self) -> Result<(), ContextError> {
+		match self.0.borrow_mut().source {
+			Source::Owned(ref mut owned) => owned.display.terminate(),
+			Source::Foreign(_) => Ok(()),
+		}
+	}
+
+	/// If `destroy()` has been called on this context.
+	pub(crate) fn is_destroyed(&self) -> bool {
+		self.get().is_destroyed()
+	}
+
+	/// Forget which buffer, texture and program are bound, for when other
+	/// code may have changed them (see `from_loader()`).  The next bind of
+	/// each is always made.
+	pub fn invalidate_bindings(&self) {
+		let context = self.get();
+
+		context.bound_program.set(0);
+		context.bound_texture.set(0);
+		context.bound_buffer.set(u32::MAX);
+	}
+
+	/// Forget that the object `name` is bound before it's deleted, since
+	/// the name may be reused.
+	pub(crate) fn forget_binding(&self, binding: Binding, name: GLuint) {
+		let context = self.get();
+		let (bound, none) = match binding {
+			Binding::Program => (&context.bound_program, 0),
+			Binding::Texture => (&context.bound_texture, 0),
+			Binding::Buffer => (&context.bound_buffer, u32::MAX),
+		};
+
+		if bound.get() == name {
+			bound.set(none);
+		}
 	}
 
 	/// Enable something
 	pub fn enable(&self, what: Feature) {
-		gl!(self, (self.get().enable)(what as u32))
+		gl!(self, enable(what as u32))
 	}
 
 	/// Disable something
 	pub fn disable(&self, what: Feature) {
-		gl!(self, (self.get().disable)(what as u32))
+		gl!(self, disable(what as u32))
 	}
 
 	/// Configure blending
@@ -239,7 +903,7 @@ impl OpenGL {
 		const GL_ONE_MINUS_SRC_ALPHA: u32 = 0x0303;
 		const GL_DST_ALPHA: u32 = 0x0304;
 
-		gl!(self, (self.get().blend_func_separate)(
+		gl!(self, blend_func_separate(
 			GL_SRC_ALPHA,
 			GL_ONE_MINUS_SRC_ALPHA,
 			GL_SRC_ALPHA,
@@ -249,11 +913,11 @@ impl OpenGL {
 
 	/// Configure stencil testing
 	pub fn stencil(&self) {
-		gl!(self, (self.get().stencil_op)(
+		gl!(self, stencil_op(
 			0x150A, 0x150A, 0x150A // GL_INVERT
 		));
 
-		gl!(self, (self.get().stencil_func)(
+		gl!(self, stencil_func(
 			0x0205, // GL_NOTEQUAL
 			0, // ≠ 0
 			0xffffffff // Mask
@@ -267,27 +931,115 @@ impl OpenGL {
 
 	/// Update the viewport.
 	pub fn viewport(&self, w: u16, h: u16) {
-		gl!(self, (self.get().viewport)(0,0,w as GLsizei,h as GLsizei));
-	}
-
-	#[cfg(not(debug_assertions))]
-	unsafe fn error(&self) { /* Do nothing in release mode for speed. */ }
-
-	#[cfg(debug_assertions)]
-	unsafe fn error(&self) {
-		match (self.get().get_error)() {
-			0 => return, // NO_ERROR
-			0x0500 => panic!("OpenGL Error: Invalid enum"),
-			0x0501 => panic!("OpenGL Error: Invalid value"),
-			0x0502 => panic!("OpenGL Error: Invalid operation"),
-			0x0503 => panic!("OpenGL Error: Stack overflow"),
-			0x0504 => panic!("OpenGL Error: Stack underflow"),
-			0x0505 => panic!("OpenGL Error: Out of memory"),
-			_ => panic!("OpenGL Error: Unknown"),
+		gl!(self, viewport(0,0,w as GLsizei,h as GLsizei));
+	}
+
+	/// Set what to do when an OpenGL call fails.
+	pub fn set_error_policy(&self, policy: ErrorPolicy) {
+		self.get().error_policy.set(policy);
+	}
+
+	/// Get and clear the errors queued by `ErrorPolicy::Collect`, oldest
+	/// first.
+	pub fn errors(&self) -> Vec<GlError> {
+		self.get().errors.borrow_mut().split_off(0)
+	}
+
+	/// Get how many uniform uploads were skipped because the value didn't
+	/// change, and how many were made.
+	pub fn uniform_stats(&self) -> UniformStats {
+		self.get().uniform_stats.get()
+	}
+
+	/// Set the uniform stats back to zero.
+	pub fn reset_uniform_stats(&self) {
+		self.get().uniform_stats.set(UniformStats::default());
+	}
+
+	#[cfg(not(any(debug_assertions, feature = "checked")))]
+	unsafe fn error(&self, _: &'static str, _: &'static str, _: u32) {
+		/* Do nothing in release mode for speed, unless `checked`. */
+	}
+
+	/// Check for errors after calling the OpenGL function `function` at
+	/// `file`:`line`, and handle them according to the error policy.
+	#[cfg(any(debug_assertions, feature = "checked"))]
+	unsafe fn error(&self, function: &'static str, file: &'static str,
+		line: u32)
+	{
+		let context = self.get();
+		let policy = context.error_policy.get();
+
+		if policy == ErrorPolicy::Ignore {
+			return;
+		}
+
+		// Several error flags may be set, but give up if a lost
+		// context keeps reporting errors.
+		for _ in 0..8 {
+			let code = match (context.functions.get_error)() {
+				0 => return, // NO_ERROR
+				code => ErrorCode::from_raw(code),
+			};
+			let error = GlError {
+				code, function, file, line
+			};
+
+			match policy {
+				ErrorPolicy::Panic => {
+					panic!("OpenGL Error: {}", error)
+				}
+				ErrorPolicy::Log => eprintln!("OpenGL Error: {}",
+					error),
+				ErrorPolicy::Collect => {
+					context.errors.borrow_mut().push(error)
+				}
+				ErrorPolicy::Ignore => unreachable!(),
+			}
+		}
+	}
+
+	/// Clear the error flags after a call that may fail, like loading a
+	/// program binary.
+	#[cfg(any(debug_assertions, feature = "checked"))]
+	fn clear_errors(&self) {
+		for _ in 0..8 {
+			if unsafe { (self.get().functions.get_error)() } == 0 {
+				break;
+			}
 		}
 	}
 
-	fn get(&self) -> std::cell::Ref<OpenGLContext> {
+	#[cfg(not(any(debug_assertions, feature = "checked")))]
+	fn clear_errors(&self) {
+		/* Errors aren't checked, so they don't need clearing. */
+	}
+
+	/// Get a string like `GL_RENDERER`, empty if there isn't one.
+	fn string(&self, name: GLenum) -> String {
+		let string = gl!(self, get_string(name));
+
+		if string.is_null() {
+			return String::new();
+		}
+
+		unsafe { std::ffi::CStr::from_ptr(string as *const _) }
+			.to_string_lossy().into_owned()
+	}
+
+	/// Check if `GL_EXTENSIONS` lists `name`.  Only for OpenGL ES and
+	/// compatibility profiles, core profiles don't have the string.
+	fn has_extension(&self, name: &str) -> bool {
+		self.string(GL_EXTENSIONS).split(' ').any(|e| e == name)
+	}
+
+	/// If program binaries are core functions (OpenGL ES 3.0, OpenGL 4.1),
+	/// rather than `GL_OES_get_program_binary`.
+	pub(crate) fn core_binary(&self) -> bool {
+		self.get().version.at_least((3, 0), (4, 1))
+	}
+
+	fn get(&self) -> std::cell::Ref<'_, OpenGLContext> {
 		self.0.borrow()
 	}
 }
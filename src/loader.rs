@@ -4,7 +4,15 @@
 // https://www.boost.org/LICENSE_1_0.txt)
 
 use c_void;
-use std::{ mem, ptr };
+use config::{ Config, ConfigInfo, Profile, Version };
+#[cfg(not(windows))]
+use config::Platform;
+use ContextError;
+use std::{ mem, ptr, sync::Once };
+#[cfg(not(windows))]
+use std::sync::Mutex;
+#[cfg(not(windows))]
+use std::{ ffi::CStr, os::raw::c_char };
 use types::*;
 
 #[cfg(windows)]
@@ -17,7 +25,9 @@ use winapi::shared::{
 dl_api!(WinOpenGL, "opengl32.dll",
 	fn wglGetProcAddress(LPCSTR) -> *mut c_void,
 	fn wglCreateContext(*mut c_void) -> *mut c_void,
-	fn wglMakeCurrent(*mut c_void, *mut c_void) -> BOOL
+	fn wglMakeCurrent(*mut c_void, *mut c_void) -> BOOL,
+	fn wglDeleteContext(*mut c_void) -> BOOL,
+	fn wglGetCurrentContext() -> *mut c_void
 );
 
 #[cfg(not(windows))]
@@ -37,54 +47,274 @@ dl_api!(UnixEGL, "libEGL.so.1",
 		*const EGLint) -> EGLSurface,
 	fn eglMakeCurrent(EGLDisplay, EGLSurface, EGLSurface, EGLContext)
 		-> EGLBoolean,
-	fn eglSwapInterval(EGLDisplay, EGLint) -> EGLBoolean
+	fn eglSwapInterval(EGLDisplay, EGLint) -> EGLBoolean,
+	fn eglCreatePbufferSurface(EGLDisplay, EGLConfig, *const EGLint)
+		-> EGLSurface,
+	fn eglQueryString(EGLDisplay, EGLint) -> *const c_char,
+	fn eglGetError() -> EGLint,
+	fn eglDestroySurface(EGLDisplay, EGLSurface) -> EGLBoolean,
+	fn eglDestroyContext(EGLDisplay, EGLContext) -> EGLBoolean,
+	fn eglTerminate(EGLDisplay) -> EGLBoolean,
+	fn eglGetCurrentContext() -> EGLContext
 );
 
 #[cfg(windows)]
 extern "system" {
 	fn SwapBuffers(a: *mut c_void) -> i32;
 	fn GetDC(a: *mut c_void) -> *mut c_void;
+	fn ReleaseDC(a: *mut c_void, b: *mut c_void) -> i32;
 	fn ChoosePixelFormat(a: *mut c_void, b: *const PixelFormatDescriptor)
 		-> i32;
 	fn SetPixelFormat(a: *mut c_void, b: i32,
 		c: *const PixelFormatDescriptor) -> i32;
+	fn GetLastError() -> u32;
+}
+
+/// Functions needed to tear down the display, copied out of `Lib` so that
+/// `Display` can clean up after itself when dropped.
+#[cfg(windows)]
+#[derive(Copy, Clone)]
+struct Teardown {
+	make_current: unsafe extern "system" fn(*mut c_void, *mut c_void)
+		-> BOOL,
+	delete_context: unsafe extern "system" fn(*mut c_void) -> BOOL,
+	get_current_context: unsafe extern "system" fn() -> *mut c_void,
 }
 
 #[cfg(windows)]
 pub struct Display {
 	dc: Option<ptr::NonNull<c_void>>, // A Windows Device Context
+	window: *mut c_void,
+	context: *mut c_void,
+	teardown: Teardown,
+	request: Config,
+	/// Only the requested config, Windows configs aren't enumerated.
+	pub configs: Vec<ConfigInfo>,
+	/// Index of the config in use.
+	pub chosen: usize,
+	/// Always a legacy context, `versions` aren't supported yet.
+	pub version: Version,
+	/// Swap interval, if it's been set.
+	swap_interval: Option<i32>,
 }
 
 #[cfg(windows)]
 impl Display {
 	// Swap surface with screen buffer.
-	pub fn swap(&self) {
-		if unsafe {
-			SwapBuffers(self.dc.unwrap().as_ptr())
-		} == 0 {
-			panic!("Swapping Failed");
+	pub fn swap(&self) -> Result<(), ContextError> {
+		// Nothing to swap if the window was released.
+		let dc = match self.dc {
+			Some(dc) => dc.as_ptr(),
+			None => return Ok(()),
+		};
+
+		if unsafe { SwapBuffers(dc) } == 0 {
+			return Err(ContextError::Swap(
+				unsafe { GetLastError() } as i32));
+		}
+
+		Ok(())
+	}
+
+	/// Release and delete the context, then release the device context.
+	/// Does nothing if already terminated.
+	pub fn terminate(&mut self) -> Result<(), ContextError> {
+		let t = self.teardown;
+		let mut result = Ok(());
+
+		if !self.context.is_null() {
+			unsafe {
+				if (t.get_current_context)() == self.context
+					&& (t.make_current)(ptr::null_mut(),
+						ptr::null_mut()) == 0
+				{
+					result = Err(ContextError::MakeCurrent(
+						GetLastError() as i32));
+				}
+				if (t.delete_context)(self.context) == 0
+					&& result.is_ok()
+				{
+					result = Err(ContextError::DestroyContext(
+						GetLastError() as i32));
+				}
+			}
+			self.context = ptr::null_mut();
+		}
+
+		if let Some(dc) = self.dc.take() {
+			unsafe { ReleaseDC(self.window, dc.as_ptr()) };
 		}
+
+		result
+	}
+
+	/// If `terminate()` has been called.
+	pub fn is_terminated(&self) -> bool {
+		self.context.is_null()
+	}
+}
+
+#[cfg(windows)]
+impl Drop for Display {
+	fn drop(&mut self) {
+		let _ = self.terminate();
+	}
+}
+
+/// Functions needed to tear down the display, copied out of `Lib` so that
+/// `Display` can clean up after itself when dropped.
+#[cfg(not(windows))]
+#[derive(Copy, Clone)]
+struct Teardown {
+	make_current: unsafe extern "system" fn(EGLDisplay, EGLSurface,
+		EGLSurface, EGLContext) -> EGLBoolean,
+	destroy_surface: unsafe extern "system" fn(EGLDisplay, EGLSurface)
+		-> EGLBoolean,
+	destroy_context: unsafe extern "system" fn(EGLDisplay, EGLContext)
+		-> EGLBoolean,
+	terminate: unsafe extern "system" fn(EGLDisplay) -> EGLBoolean,
+	get_current_context: unsafe extern "system" fn() -> EGLContext,
+	get_error: unsafe extern "system" fn() -> EGLint,
+}
+
+/// How many `Display`s use each EGL display that isn't reference counted
+/// by the driver.  `eglGetDisplay()` returns the same display every time,
+/// and `eglTerminate()` ends it for all of them.
+#[cfg(not(windows))]
+static DISPLAYS: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+
+/// Count another `Display` on `display`, after initializing it.
+#[cfg(not(windows))]
+fn acquire(display: EGLDisplay, tracked: bool) {
+	if tracked {
+		return;
+	}
+
+	let mut displays = DISPLAYS.lock().unwrap_or_else(|e| e.into_inner());
+
+	match displays.iter_mut().find(|d| d.0 == display as usize) {
+		Some(d) => d.1 += 1,
+		None => displays.push((display as usize, 1)),
+	}
+}
+
+/// Stop counting a `Display` on `display`.  Returns true if it should be
+/// terminated, because it was the last one or the driver counts them.
+#[cfg(not(windows))]
+fn release(display: EGLDisplay, tracked: bool) -> bool {
+	if tracked {
+		return true;
+	}
+
+	let mut displays = DISPLAYS.lock().unwrap_or_else(|e| e.into_inner());
+	let index = match displays.iter().position(|d| d.0 == display as usize)
+	{
+		Some(index) => index,
+		None => return true,
+	};
+
+	displays[index].1 -= 1;
+	if displays[index].1 > 0 {
+		return false;
 	}
+	displays.swap_remove(index);
+	true
 }
 
 #[cfg(not(windows))]
 pub struct Display {
+	teardown: Teardown,
 	display: *mut c_void,
+	// If the driver counts references (`EGL_KHR_display_reference`).
+	tracked: bool,
 	surface: Option<ptr::NonNull<c_void>>,
 	config: *mut c_void,
 	context: *mut c_void,
+	/// All of the configs that matched the request.
+	pub configs: Vec<ConfigInfo>,
+	/// Index of the config in use.
+	pub chosen: usize,
+	/// The version that the context was created for.
+	pub version: Version,
+	/// Swap interval to use for window surfaces.
+	swap_interval: EGLint,
 }
 
 #[cfg(not(windows))]
 impl Display {
 	// Swap surface with screen buffer.
-	pub fn swap(&self, lib: &Lib) {
+	pub fn swap(&self, lib: &Lib) -> Result<(), ContextError> {
+		// Nothing to swap if surfaceless.
+		let surface = match self.surface {
+			Some(surface) => surface.as_ptr(),
+			None => return Ok(()),
+		};
+
 		if unsafe {
-			(lib.gl.eglSwapBuffers)(self.display,
-				self.surface.unwrap().as_ptr())
+			(lib.gl.eglSwapBuffers)(self.display, surface)
 		} == 0 {
-			panic!("Swapping Failed");
+			return Err(ContextError::Swap(lib.error()));
+		}
+
+		Ok(())
+	}
+
+	/// Release the context if current, destroy the surface and context,
+	/// then terminate the display if no other `Display` uses it.  Every
+	/// step is attempted even if one fails, the first error is returned.
+	/// Does nothing if already terminated.
+	pub fn terminate(&mut self) -> Result<(), ContextError> {
+		if self.display.is_null() {
+			return Ok(());
+		}
+
+		let t = self.teardown;
+		let mut result = Ok(());
+		let mut check = |ok: EGLBoolean, error: fn(i32) -> ContextError| {
+			if ok == 0 && result.is_ok() {
+				result = Err(error(unsafe { (t.get_error)() }));
+			}
+		};
+
+		unsafe {
+			if !self.context.is_null()
+				&& (t.get_current_context)() == self.context
+			{
+				check((t.make_current)(self.display,
+					EGL_NO_SURFACE, EGL_NO_SURFACE,
+					EGL_NO_CONTEXT), ContextError::MakeCurrent);
+			}
+			if let Some(surface) = self.surface.take() {
+				check((t.destroy_surface)(self.display,
+					surface.as_ptr()),
+					ContextError::DestroySurface);
+			}
+			if !self.context.is_null() {
+				check((t.destroy_context)(self.display,
+					self.context), ContextError::DestroyContext);
+			}
+			if release(self.display, self.tracked) {
+				check((t.terminate)(self.display),
+					ContextError::Terminate);
+			}
 		}
+
+		self.context = ptr::null_mut();
+		self.display = ptr::null_mut();
+
+		result
+	}
+
+	/// If `terminate()` has been called.
+	pub fn is_terminated(&self) -> bool {
+		self.display.is_null()
+	}
+}
+
+#[cfg(not(windows))]
+impl Drop for Display {
+	fn drop(&mut self) {
+		let _ = self.terminate();
 	}
 }
 
@@ -96,100 +326,457 @@ pub struct Lib {
 }
 
 impl Lib {
-	/// Load the OpenGL libary.  `None` if can't find it.
-	pub fn new() -> Result<Self, ::dl_api::Error> {
+	/// Load the OpenGL libary.  `Err` if can't find it.
+	///
+	/// The first call leaks one reference to the library, so it's never
+	/// unloaded for the rest of the process.  `EGLDeviceEXT` handles from
+	/// `devices()` belong to the library, and would dangle once the `Lib`
+	/// that got them is dropped otherwise.
+	pub fn new() -> Result<Self, ContextError> {
 		#[cfg(windows)] type Gl = WinOpenGL;
 		#[cfg(not(windows))] type Gl = UnixEGL;
 
-		Ok(Lib { gl: Gl::new()? })
+		static KEEP_LOADED: Once = Once::new();
+
+		let gl = Gl::new().map_err(ContextError::Library)?;
+
+		KEEP_LOADED.call_once(|| {
+			if let Ok(gl) = Gl::new() {
+				mem::forget(gl);
+			}
+		});
+
+		Ok(Lib { gl })
+	}
+
+	/// Get the error code of the last failed call.
+	#[cfg(not(windows))]
+	fn error(&self) -> i32 {
+		unsafe { (self.gl.eglGetError)() }
+	}
+
+	/// Get the error code of the last failed call.
+	#[cfg(windows)]
+	fn error(&self) -> i32 {
+		unsafe { GetLastError() as i32 }
 	}
 
 	/// Initialize the opengl (connect to the display)
 	#[cfg(windows)]
-	pub fn init(&self) -> (Display, i32) {
-		(Display {
+	pub fn init(&self, request: &Config, _headless: bool)
+		-> Result<(Display, i32), ContextError>
+	{
+		Ok((Display {
 			dc: None,
-		}, 0)
+			window: ptr::null_mut(),
+			context: ptr::null_mut(),
+			teardown: Teardown {
+				make_current: self.gl.wglMakeCurrent,
+				delete_context: self.gl.wglDeleteContext,
+				get_current_context: self.gl.wglGetCurrentContext,
+			},
+			request: request.clone(),
+			configs: vec![ConfigInfo {
+				id: 0,
+				red: 8,
+				green: 8,
+				blue: 8,
+				alpha: request.alpha as i32,
+				depth: request.depth as i32,
+				stencil: request.stencil as i32,
+				samples: 0,
+				slow: false,
+			}],
+			chosen: 0,
+			version: Version::Gl(2, 0, Profile::Compatibility),
+			swap_interval: None,
+		}, 0))
 	}
 	
-	/// Initialize the opengl (connect to the display)
+	/// Initialize the opengl (connect to the display).  If `headless` is
+	/// set, the config must support pbuffers.
 	#[cfg(not(windows))]
-	pub fn init(&self) -> (Display, i32) {
-		let display = unsafe {
-			(self.gl.eglGetDisplay)(ptr::null_mut())
+	pub fn init(&self, request: &Config, headless: bool)
+		-> Result<(Display, i32), ContextError>
+	{
+		let (display, tracked) = match request.platform {
+			// Prefer surfaceless, so no window system is needed.  A
+			// driver without it may also return a null display.
+			Platform::Default if headless => self
+				.connect(Platform::Surfaceless)
+				.or_else(|_| self.connect(Platform::Default))?,
+			platform => self.connect(platform)?,
+		};
+
+		// Try each version in order of preference, keeping the last error.
+		let mut error = ContextError::NoConfigs;
+		let mut created = None;
+
+		for version in &request.versions {
+			match self.create(display, request, *version, headless) {
+				Ok(c) => {
+					created = Some((c, *version));
+					break;
+				}
+				Err(e) => error = e,
+			}
+		}
+
+		let ((context, config, configs, chosen), version) = match created {
+			Some(created) => created,
+			None => {
+				if release(display, tracked) {
+					unsafe { (self.gl.eglTerminate)(display) };
+				}
+				return Err(error);
+			}
 		};
+
+		let surface = None;
+
+		// Get visual id (0 if there is none, like when headless).
+		let mut visual_id = 0;
+		unsafe {
+			(self.gl.eglGetConfigAttrib)(display, config,
+				EGL_NATIVE_VISUAL_ID, &mut visual_id);
+		}
+
+		Ok((Display {
+			teardown: self.teardown(),
+			display,
+			tracked,
+			surface,
+			config,
+			context,
+			configs,
+			chosen,
+			version,
+			// Synchronize buffer swaps to monitor refresh rate.
+			swap_interval: 1,
+		}, visual_id))
+	}
+
+	/// Get and initialize the display for `platform`, and if the driver
+	/// counts its references.
+	#[cfg(not(windows))]
+	fn connect(&self, platform: Platform)
+		-> Result<(EGLDisplay, bool), ContextError>
+	{
+		let (display, tracked) = self.platform_display(platform)?;
+
 		if display.is_null() {
-			panic!("EGL: Couldn't load display.");
+			return Err(ContextError::NoDisplay(self.error()));
 		}
 
 		if unsafe {
 			(self.gl.eglInitialize)(display, ptr::null_mut(),
 				ptr::null_mut())
 		} == 0 {
-			panic!("Couldn't initialize EGL");
+			return Err(ContextError::Initialize(self.error()));
+		}
+		acquire(display, tracked);
+
+		Ok((display, tracked))
+	}
+
+	/// Copy the functions needed for `Display::terminate()`.
+	#[cfg(not(windows))]
+	fn teardown(&self) -> Teardown {
+		Teardown {
+			make_current: self.gl.eglMakeCurrent,
+			destroy_surface: self.gl.eglDestroySurface,
+			destroy_context: self.gl.eglDestroyContext,
+			terminate: self.gl.eglTerminate,
+			get_current_context: self.gl.eglGetCurrentContext,
+			get_error: self.gl.eglGetError,
 		}
+	}
 
+	/// Choose a config and create a context for one version.
+	#[cfg(not(windows))]
+	fn create(&self, display: EGLDisplay, request: &Config,
+		version: Version, headless: bool)
+		-> Result<(EGLContext, EGLConfig, Vec<ConfigInfo>, usize),
+			ContextError>
+	{
 		// Config
-		let mut config = ptr::null_mut();
-		let mut nconfigs = unsafe { mem::uninitialized() };
+		let surface_type = if headless {
+			EGL_PBUFFER_BIT
+		} else {
+			EGL_WINDOW_BIT
+		};
+		let mut attribs = vec![
+			EGL_SURFACE_TYPE, surface_type,
+			EGL_RENDERABLE_TYPE, version.renderable(),
+			EGL_RED_SIZE, 8,
+			EGL_GREEN_SIZE, 8,
+			EGL_BLUE_SIZE, 8,
+			EGL_ALPHA_SIZE, request.alpha as EGLint,
+			EGL_DEPTH_SIZE, request.depth as EGLint,
+			EGL_STENCIL_SIZE, request.stencil as EGLint,
+		];
+		if request.samples != 0 {
+			attribs.extend_from_slice(&[
+				EGL_SAMPLE_BUFFERS, 1,
+				EGL_SAMPLES, request.samples as EGLint,
+			]);
+		}
+		attribs.push(EGL_NONE);
+
+		let mut nconfigs = 0;
 
 		if unsafe {
-			(self.gl.eglChooseConfig)(display, [
-				EGL_RED_SIZE, 8,
-				EGL_GREEN_SIZE, 8,
-				EGL_BLUE_SIZE, 8,
-				EGL_DEPTH_SIZE, 24,
-				EGL_NONE
-			].as_ptr(), &mut config, 1, &mut nconfigs)
+			(self.gl.eglChooseConfig)(display, attribs.as_ptr(),
+				ptr::null_mut(), 0, &mut nconfigs)
 		} == 0 {
-			panic!("Couldn't choose the config");
+			return Err(ContextError::ChooseConfig(self.error()));
 		}
 
 		if nconfigs == 0 {
-			panic!("No configs!");
+			return Err(ContextError::NoConfigs);
+		}
+
+		let mut egl_configs = vec![ptr::null_mut(); nconfigs as usize];
+
+		if unsafe {
+			(self.gl.eglChooseConfig)(display, attribs.as_ptr(),
+				egl_configs.as_mut_ptr(), nconfigs, &mut nconfigs)
+		} == 0 {
+			return Err(ContextError::ChooseConfig(self.error()));
 		}
+		egl_configs.truncate(nconfigs as usize);
 
-		if unsafe { (self.gl.eglBindAPI)(EGL_OPENGL_ES_API) } == 0 {
-			panic!("Couldn't bind OpenGLES");
+		let configs: Vec<ConfigInfo> = egl_configs.iter()
+			.map(|config| self.config_info(display, *config))
+			.collect();
+
+		// Pick the best scoring config, ties go to the driver's order.
+		let mut chosen = 0;
+		for i in 1..configs.len() {
+			if (request.score)(request, &configs[i])
+				> (request.score)(request, &configs[chosen])
+			{
+				chosen = i;
+			}
+		}
+		let config = egl_configs[chosen];
+
+		// Context attributes.  OpenGL ES 2.0 only uses the EGL 1.4
+		// attribute, the others need EGL 1.5 or EGL_KHR_create_context.
+		let (api, mut attribs) = match version {
+			Version::GlEs(major, minor) => (EGL_OPENGL_ES_API, vec![
+				EGL_CONTEXT_MAJOR_VERSION, major as EGLint,
+				EGL_CONTEXT_MINOR_VERSION, minor as EGLint,
+			]),
+			Version::Gl(major, minor, profile) => (EGL_OPENGL_API, vec![
+				EGL_CONTEXT_MAJOR_VERSION, major as EGLint,
+				EGL_CONTEXT_MINOR_VERSION, minor as EGLint,
+				EGL_CONTEXT_OPENGL_PROFILE_MASK, match profile {
+					Profile::Core =>
+					EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+					Profile::Compatibility =>
+					EGL_CONTEXT_OPENGL_COMPATIBILITY_PROFILE_BIT,
+				},
+			]),
+		};
+		if version == Version::GlEs(2, 0) {
+			attribs.truncate(2);
+		}
+		attribs.push(EGL_NONE);
+
+		if unsafe { (self.gl.eglBindAPI)(api) } == 0 {
+			return Err(ContextError::BindApi(self.error()));
 		}
 
 		// Create an EGL rendering context.
 		let context = unsafe {
 			(self.gl.eglCreateContext)(display, config,
-				ptr::null_mut(),
-				[EGL_CONTEXT_CLIENT_VERSION, 2, EGL_NONE]
-					.as_ptr()
-			)
+				ptr::null_mut(), attribs.as_ptr())
 		};
 
 		if context.is_null() {
-			panic!("Couldn't create EGL rendering context.");
+			return Err(ContextError::CreateContext(self.error()));
 		}
 
-		let surface = None;
+		Ok((context, config, configs, chosen))
+	}
+
+	/// Query one attribute of a framebuffer config.
+	#[cfg(not(windows))]
+	fn config_attrib(&self, display: EGLDisplay, config: EGLConfig,
+		attribute: EGLint) -> EGLint
+	{
+		let mut value = 0;
+		unsafe {
+			(self.gl.eglGetConfigAttrib)(display, config, attribute,
+				&mut value);
+		}
+		value
+	}
+
+	/// Query the attributes of a framebuffer config.
+	#[cfg(not(windows))]
+	fn config_info(&self, display: EGLDisplay, config: EGLConfig)
+		-> ConfigInfo
+	{
+		let attrib = |attribute| {
+			self.config_attrib(display, config, attribute)
+		};
+
+		ConfigInfo {
+			id: attrib(EGL_CONFIG_ID),
+			red: attrib(EGL_RED_SIZE),
+			green: attrib(EGL_GREEN_SIZE),
+			blue: attrib(EGL_BLUE_SIZE),
+			alpha: attrib(EGL_ALPHA_SIZE),
+			depth: attrib(EGL_DEPTH_SIZE),
+			stencil: attrib(EGL_STENCIL_SIZE),
+			samples: attrib(EGL_SAMPLES),
+			slow: attrib(EGL_CONFIG_CAVEAT) == EGL_SLOW_CONFIG,
+		}
+	}
+
+	/// Get a display on a specific platform with
+	/// `eglGetPlatformDisplayEXT()`.
+	#[cfg(not(windows))]
+	fn platform_display(&self, platform: Platform)
+		-> Result<(EGLDisplay, bool), ContextError>
+	{
+		let (platform, native, extensions): (_, _, &[&'static str]) =
+			match platform
+		{
+			Platform::Default => return Ok((unsafe {
+				(self.gl.eglGetDisplay)(EGL_DEFAULT_DISPLAY)
+			}, false)),
+			Platform::X11(display) => (EGL_PLATFORM_X11_KHR,
+				display, &["EGL_KHR_platform_x11",
+					"EGL_EXT_platform_x11"]),
+			Platform::Wayland(display) => (EGL_PLATFORM_WAYLAND_KHR,
+				display, &["EGL_KHR_platform_wayland",
+					"EGL_EXT_platform_wayland"]),
+			Platform::Gbm(device) => (EGL_PLATFORM_GBM_KHR,
+				device, &["EGL_KHR_platform_gbm",
+					"EGL_MESA_platform_gbm"]),
+			Platform::Surfaceless => (EGL_PLATFORM_SURFACELESS_MESA,
+				EGL_DEFAULT_DISPLAY,
+				&["EGL_MESA_platform_surfaceless"]),
+			Platform::Device(device) => (EGL_PLATFORM_DEVICE_EXT,
+				device, &["EGL_EXT_platform_device"]),
+		};
+
+		// Client extensions are queried without a display.
+		if !extensions.iter().any(|extension| {
+			self.has_extension(EGL_NO_DISPLAY, extension)
+		}) {
+			return Err(ContextError::Unsupported(extensions[0]));
+		}
+
+		let get_platform_display: unsafe extern "system" fn(
+			EGLenum, *mut c_void, *const EGLint) -> EGLDisplay
+			= self.load_opt(b"eglGetPlatformDisplayEXT\0").ok_or(
+				ContextError::Unsupported("EGL_EXT_platform_base")
+			)?;
+
+		// Have the driver count `eglInitialize()`s if it can, so other
+		// libraries on the display aren't terminated with it.
+		let tracked = self.has_extension(EGL_NO_DISPLAY,
+			"EGL_KHR_display_reference");
+		let attributes = [EGL_TRACK_REFERENCES_KHR, 1, EGL_NONE];
+		let attributes = if tracked {
+			attributes.as_ptr()
+		} else {
+			ptr::null()
+		};
+
+		Ok((unsafe {
+			get_platform_display(platform, native, attributes)
+		}, tracked))
+	}
+
+	/// Get the EGL devices, for use with `Platform::Device`.
+	#[cfg(not(windows))]
+	pub fn devices(&self) -> Result<Vec<*mut c_void>, ContextError> {
+		if !self.has_extension(EGL_NO_DISPLAY,
+			"EGL_EXT_device_enumeration")
+		{
+			return Err(ContextError::Unsupported(
+				"EGL_EXT_device_enumeration"));
+		}
+
+		let query_devices: unsafe extern "system" fn(EGLint,
+			*mut *mut c_void, *mut EGLint) -> EGLBoolean
+			= self.load(b"eglQueryDevicesEXT\0")?;
+		let mut ndevices = 0;
 
-		// Get visual id
-		let mut visual_id = unsafe { mem::uninitialized() };
 		if unsafe {
-			(self.gl.eglGetConfigAttrib)(display, config,
-				EGL_NATIVE_VISUAL_ID, &mut visual_id)
+			query_devices(0, ptr::null_mut(), &mut ndevices)
 		} == 0 {
-			panic!("couldn't get visual id");
+			return Err(ContextError::NoDisplay(self.error()));
 		}
 
-		(Display {
-			display,
-			surface,
-			config,
-			context,
-		}, visual_id)
+		let mut devices = vec![ptr::null_mut(); ndevices as usize];
+
+		if unsafe {
+			query_devices(ndevices, devices.as_mut_ptr(),
+				&mut ndevices)
+		} == 0 {
+			return Err(ContextError::NoDisplay(self.error()));
+		}
+		devices.truncate(ndevices as usize);
+
+		Ok(devices)
 	}
-	
+
+	/// Check if an extension is in the space-separated list returned by
+	/// `eglQueryString()`.  Client extensions are queried with
+	/// `EGL_NO_DISPLAY`.
+	#[cfg(not(windows))]
+	pub fn has_extension(&self, display: EGLDisplay, name: &str) -> bool {
+		let extensions = unsafe {
+			(self.gl.eglQueryString)(display, EGL_EXTENSIONS)
+		};
+
+		if extensions.is_null() {
+			return false;
+		}
+
+		unsafe { CStr::from_ptr(extensions) }.to_str().unwrap_or("")
+			.split(' ').any(|extension| extension == name)
+	}
+
+	#[cfg(windows)]
+	pub fn init2(&self, display: &mut Display, window: *mut c_void)
+		-> Result<(), ContextError>
+	{
+		let dc = self.attach(display, window)?;
+		let context = unsafe { (self.gl.wglCreateContext)(dc) };
+
+		if context.is_null() {
+			return Err(ContextError::CreateContext(self.error()));
+		}
+
+		display.context = context;
+
+		if unsafe { (self.gl.wglMakeCurrent)(dc, context) } == 0 {
+			return Err(ContextError::MakeCurrent(self.error()));
+		}
+
+		Ok(())
+	}
+
+	/// Get the device context for a window, and set it's pixel format.
 	#[cfg(windows)]
-	pub fn init2(&self, display: &mut Display, window: *mut c_void) {
+	fn attach(&self, display: &mut Display, window: *mut c_void)
+		-> Result<*mut c_void, ContextError>
+	{
 		let dc = unsafe { GetDC(window) };
-	
+
+		if dc.is_null() {
+			return Err(ContextError::CreateSurface(self.error()));
+		}
+
 		display.dc = ptr::NonNull::new(dc);
+		display.window = window;
 		
 		let pixel_format = PixelFormatDescriptor {
 			n_size: mem::size_of::<PixelFormatDescriptor>() as u16,
@@ -200,11 +787,12 @@ impl Lib {
 			c_color_bits: 24,
 			c_red_bits: 0, c_red_shift: 0, c_green_bits: 0,
 			c_green_shift: 0, c_blue_bits: 0, c_blue_shift: 0,
-			c_alpha_bits: 0, c_alpha_shift: 0, c_accum_bits: 0,
+			c_alpha_bits: display.request.alpha, c_alpha_shift: 0,
+			c_accum_bits: 0,
 			c_accum_red_bits: 0, c_accum_green_bits: 0,
 			c_accum_blue_bits: 0, c_accum_alpha_bits: 0,
-			c_depth_bits: 24,
-			c_stencil_bits: 8, c_aux_buffers: 0,
+			c_depth_bits: display.request.depth,
+			c_stencil_bits: display.request.stencil, c_aux_buffers: 0,
 			i_layer_type: 0 /*main-plane*/,
 			b_reserved: 0, dw_layer_mask: 0, dw_visible_mask: 0,
 			dw_damage_mask: 0,
@@ -213,18 +801,114 @@ impl Lib {
 		let format = unsafe {
 			ChoosePixelFormat(dc, &pixel_format)
 		};
-		
-		unsafe {
-			SetPixelFormat(dc, format, &pixel_format);
-			
-			let context = (self.gl.wglCreateContext)(dc);
-			(self.gl.wglMakeCurrent)(dc, context);
+
+		if format == 0 || unsafe {
+			SetPixelFormat(dc, format, &pixel_format)
+		} == 0 {
+			return Err(ContextError::ChooseConfig(self.error()));
+		}
+
+		Ok(dc)
+	}
+
+	/// Render to a different window, keeping the context.
+	#[cfg(windows)]
+	pub fn set_window(&self, display: &mut Display, window: *mut c_void)
+		-> Result<(), ContextError>
+	{
+		self.release_window(display)?;
+
+		let dc = self.attach(display, window)?;
+
+		if unsafe { (self.gl.wglMakeCurrent)(dc, display.context) } == 0 {
+			return Err(ContextError::MakeCurrent(self.error()));
+		}
+
+		if let Some(interval) = display.swap_interval {
+			self.set_swap_interval(display, interval)?;
 		}
+
+		Ok(())
+	}
+
+	/// Set how many vertical blanks to wait for before swapping, negative
+	/// for adaptive vsync.
+	#[cfg(windows)]
+	pub fn set_swap_interval(&self, display: &mut Display, interval: i32)
+		-> Result<(), ContextError>
+	{
+		let swap_interval: unsafe extern "system" fn(i32) -> BOOL =
+			self.load(b"wglSwapIntervalEXT\0").map_err(|_| {
+				ContextError::Unsupported("WGL_EXT_swap_control")
+			})?;
+
+		if interval < 0 && !self.has_wgl_extension(
+			"WGL_EXT_swap_control_tear")
+		{
+			return Err(ContextError::Unsupported(
+				"WGL_EXT_swap_control_tear"));
+		}
+
+		if unsafe { swap_interval(interval) } == 0 {
+			return Err(ContextError::SwapInterval(self.error()));
+		}
+
+		display.swap_interval = Some(interval);
+
+		Ok(())
+	}
+
+	/// Check if a WGL extension is supported by the current context.
+	#[cfg(windows)]
+	fn has_wgl_extension(&self, name: &str) -> bool {
+		let get_extensions: unsafe extern "system" fn()
+			-> *const ::std::os::raw::c_char =
+			match self.load(b"wglGetExtensionsStringEXT\0") {
+				Ok(get_extensions) => get_extensions,
+				Err(_) => return false,
+			};
+		let extensions = unsafe { get_extensions() };
+
+		if extensions.is_null() {
+			return false;
+		}
+
+		unsafe { ::std::ffi::CStr::from_ptr(extensions) }.to_str()
+			.unwrap_or("").split(' ').any(|extension| {
+				extension == name
+			})
+	}
+
+	/// Stop rendering to the window, keeping the context.
+	#[cfg(windows)]
+	pub fn release_window(&self, display: &mut Display)
+		-> Result<(), ContextError>
+	{
+		if let Some(dc) = display.dc.take() {
+			let result = unsafe {
+				(self.gl.wglMakeCurrent)(ptr::null_mut(),
+					ptr::null_mut())
+			};
+			if result == 0 {
+				let error = self.error();
+				unsafe { ReleaseDC(display.window, dc.as_ptr()) };
+				return Err(ContextError::MakeCurrent(error));
+			}
+
+			if unsafe { ReleaseDC(display.window, dc.as_ptr()) } == 0 {
+				return Err(ContextError::DestroySurface(
+					self.error()));
+			}
+		}
+
+		Ok(())
 	}
 
 	/// Initialize the opengl (connect to the display) STEP 2
 	#[cfg(not(windows))]
-	pub fn init2(&self, display: &mut Display, window: EGLNativeWindowType){
+	pub fn init2(&self, display: &mut Display, window: EGLNativeWindowType)
+		-> Result<(), ContextError>
+	{
 		// Create surface
 		let surface = unsafe {
 			(self.gl.eglCreateWindowSurface)(display.display,
@@ -232,66 +916,330 @@ impl Lib {
 		};
 
 		if surface.is_null() {
-			panic!("Couldn't create EGL surface.");
+			return Err(ContextError::CreateSurface(self.error()));
 		}
 
-		// Connect context to surface
+		self.make_current(display, surface)?;
+
 		if unsafe {
-			(self.gl.eglMakeCurrent)(display.display, surface,
-				surface, display.context)
+			(self.gl.eglSwapInterval)(display.display,
+				display.swap_interval)
 		} == 0 {
-			panic!("Couldn't make current");
+			return Err(ContextError::SwapInterval(self.error()));
 		}
 
-		// Synchronize buffer swaps to monitor refresh rate.
-		unsafe { (self.gl.eglSwapInterval)(display.display, 1) };
+		Ok(())
+	}
 
-		// Guaranteed to be `Some` because of conditional panic above.
-		display.surface = ptr::NonNull::new(surface);
+	/// Initialize the opengl (connect to the display) STEP 2, rendering
+	/// to an offscreen pbuffer instead of a window.
+	#[cfg(not(windows))]
+	pub fn init2_pbuffer(&self, display: &mut Display, w: u16, h: u16)
+		-> Result<(), ContextError>
+	{
+		let surface = unsafe {
+			(self.gl.eglCreatePbufferSurface)(display.display,
+				display.config, [
+					EGL_WIDTH, w as EGLint,
+					EGL_HEIGHT, h as EGLint,
+					EGL_NONE
+				].as_ptr())
+		};
+
+		if surface.is_null() {
+			return Err(ContextError::CreateSurface(self.error()));
+		}
+
+		self.make_current(display, surface)
 	}
 
+	/// Initialize the opengl (connect to the display) STEP 2, without any
+	/// surface.  Rendering must go to framebuffer objects.
 	#[cfg(not(windows))]
-	fn load_check(&self, name: &[u8], fn_ptr: *const c_void) {
-		if fn_ptr.is_null() {
-			panic!("couldn't load function \"{}\"!", ::std::str::from_utf8(name).unwrap());
+	pub fn init2_surfaceless(&self, display: &mut Display)
+		-> Result<(), ContextError>
+	{
+		if !self.has_extension(display.display,
+			"EGL_KHR_surfaceless_context")
+		{
+			return Err(ContextError::Unsupported(
+				"EGL_KHR_surfaceless_context"));
+		}
+
+		self.make_current(display, EGL_NO_SURFACE)
+	}
+
+	/// Render to a different window, keeping the context.
+	#[cfg(not(windows))]
+	pub fn set_window(&self, display: &mut Display,
+		window: EGLNativeWindowType) -> Result<(), ContextError>
+	{
+		self.release_window(display)?;
+		self.init2(display, window)
+	}
+
+	/// Destroy the surface, keeping the context.  The context stays
+	/// current without a surface if `EGL_KHR_surfaceless_context` is
+	/// supported.
+	#[cfg(not(windows))]
+	pub fn release_window(&self, display: &mut Display)
+		-> Result<(), ContextError>
+	{
+		let surface = match display.surface {
+			Some(surface) => surface.as_ptr(),
+			None => return Ok(()),
+		};
+
+		let context = if self.has_extension(display.display,
+			"EGL_KHR_surfaceless_context")
+		{
+			display.context
+		} else {
+			EGL_NO_CONTEXT
+		};
+
+		if unsafe {
+			(self.gl.eglMakeCurrent)(display.display,
+				EGL_NO_SURFACE, EGL_NO_SURFACE, context)
+		} == 0 {
+			return Err(ContextError::MakeCurrent(self.error()));
+		}
+		display.surface = None;
+
+		if unsafe {
+			(self.gl.eglDestroySurface)(display.display, surface)
+		} == 0 {
+			return Err(ContextError::DestroySurface(self.error()));
+		}
+
+		Ok(())
+	}
+
+	/// Set how many vertical blanks to wait for before swapping.  Must be
+	/// within the config's `EGL_MIN_SWAP_INTERVAL` and
+	/// `EGL_MAX_SWAP_INTERVAL`.  EGL has no adaptive vsync, so negative
+	/// intervals are unsupported.
+	#[cfg(not(windows))]
+	pub fn set_swap_interval(&self, display: &mut Display, interval: i32)
+		-> Result<(), ContextError>
+	{
+		let min = self.config_attrib(display.display, display.config,
+			EGL_MIN_SWAP_INTERVAL);
+		let max = self.config_attrib(display.display, display.config,
+			EGL_MAX_SWAP_INTERVAL);
+
+		if interval < 0 {
+			return Err(ContextError::Unsupported(
+				"Adaptive vsync on EGL"));
+		}
+
+		if interval < min || interval > max {
+			return Err(ContextError::InvalidSwapInterval {
+				interval, min, max
+			});
+		}
+
+		display.swap_interval = interval;
+
+		// Applied when a window surface is created if there isn't one.
+		if display.surface.is_some() && unsafe {
+			(self.gl.eglSwapInterval)(display.display, interval)
+		} == 0 {
+			return Err(ContextError::SwapInterval(self.error()));
+		}
+
+		Ok(())
+	}
+
+	/// Connect context to surface, which may be `EGL_NO_SURFACE`.  The
+	/// display owns the surface even if this fails.
+	#[cfg(not(windows))]
+	fn make_current(&self, display: &mut Display, surface: EGLSurface)
+		-> Result<(), ContextError>
+	{
+		display.surface = ptr::NonNull::new(surface);
+
+		if unsafe {
+			(self.gl.eglMakeCurrent)(display.display, surface,
+				surface, display.context)
+		} == 0 {
+			return Err(ContextError::MakeCurrent(self.error()));
 		}
+
+		Ok(())
 	}
 
 	#[cfg(not(windows))]
 	// Load an OpenGL 3 / OpenGLES 2 function.
-	pub fn load<T>(&self, name: &[u8]) -> T {
+	pub fn load<T>(&self, name: &[u8]) -> Result<T, ContextError> {
+		self.load_opt(name).ok_or_else(|| missing_symbol(name))
+	}
+
+	#[cfg(not(windows))]
+	// Load an EGL extension function.  `None` if it's not available.
+	fn load_opt<T>(&self, name: &[u8]) -> Option<T> {
 		let fn_ptr: *const c_void = unsafe {
 			(self.gl.eglGetProcAddress)(name as *const _
 				as *const i8)
 		};
 
-		self.load_check(name, fn_ptr);
-
-		unsafe { mem::transmute_copy::<*const c_void, T>(&fn_ptr) }
+		if fn_ptr.is_null() {
+			None
+		} else {
+			Some(unsafe {
+				mem::transmute_copy::<*const c_void, T>(&fn_ptr)
+			})
+		}
 	}
 
 	#[cfg(windows)]
 	// Load an OpenGL 3 / OpenGLES 2 function.
-	pub fn load<T>(&self, name: &[u8]) -> T {
+	pub fn load<T>(&self, name: &[u8]) -> Result<T, ContextError> {
 		let fn_ptr: *const c_void = unsafe {
 			(self.gl.wglGetProcAddress)(name as *const _ as LPCSTR)
 		};
-		
+
 		if fn_ptr.is_null() {
-			if let Ok(n) = unsafe {
+			return unsafe {
 				self.gl.__lib.symbol_cstr(
 					::std::ffi::CStr::from_bytes_with_nul(
 						name
 					).unwrap()
 				)
-			} {
-				return n;
-			} else {
-				panic!("couldn't load function \"{}\"!",
-					::std::str::from_utf8(name).unwrap());
-			};
+			}.map_err(|_| missing_symbol(name));
 		}
 
-		unsafe { mem::transmute_copy::<*const c_void, T>(&fn_ptr) }
+		Ok(unsafe { mem::transmute_copy::<*const c_void, T>(&fn_ptr) })
+	}
+}
+
+impl Lib {
+	/// Get the address of an OpenGL function, null if it's not available.
+	pub fn proc_address(&self, name: &str) -> *const c_void {
+		match ::std::ffi::CString::new(name) {
+			Ok(name) => self.load(name.as_bytes_with_nul())
+				.unwrap_or(::std::ptr::null()),
+			Err(_) => ::std::ptr::null(),
+		}
+	}
+}
+
+/// Error for a function that couldn't be loaded (`name` is nul-terminated).
+fn missing_symbol(name: &[u8]) -> ContextError {
+	ContextError::MissingSymbol(String::from_utf8_lossy(
+		&name[..name.len() - 1]).into_owned())
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+	use super::*;
+	use std::cell::RefCell;
+
+	thread_local!(static CALLS: RefCell<Vec<(&'static str, usize)>> =
+		const { RefCell::new(Vec::new()) });
+
+	fn record(name: &'static str, display: EGLDisplay) -> EGLBoolean {
+		CALLS.with(|c| c.borrow_mut().push((name, display as usize)));
+		1
+	}
+
+	unsafe extern "system" fn make_current(display: EGLDisplay,
+		_: EGLSurface, _: EGLSurface, _: EGLContext) -> EGLBoolean
+	{
+		record("eglMakeCurrent", display)
+	}
+
+	unsafe extern "system" fn destroy_surface(display: EGLDisplay,
+		_: EGLSurface) -> EGLBoolean
+	{
+		record("eglDestroySurface", display)
+	}
+
+	unsafe extern "system" fn destroy_context(display: EGLDisplay,
+		_: EGLContext) -> EGLBoolean
+	{
+		record("eglDestroyContext", display)
+	}
+
+	unsafe extern "system" fn terminate(display: EGLDisplay) -> EGLBoolean {
+		record("eglTerminate", display)
+	}
+
+	unsafe extern "system" fn get_current_context() -> EGLContext {
+		ptr::null_mut()
+	}
+
+	unsafe extern "system" fn get_error() -> EGLint {
+		0x3000 // EGL_SUCCESS
+	}
+
+	/// A `Display` with a context on the fake EGL display `display`, as
+	/// if `Lib::init()` connected to it.
+	fn display(display: usize, tracked: bool) -> Display {
+		let display = display as EGLDisplay;
+
+		acquire(display, tracked);
+		Display {
+			teardown: Teardown {
+				make_current,
+				destroy_surface,
+				destroy_context,
+				terminate,
+				get_current_context,
+				get_error,
+			},
+			display,
+			tracked,
+			surface: None,
+			config: ptr::null_mut(),
+			context: 1 as EGLContext,
+			configs: Vec::new(),
+			chosen: 0,
+			version: Version::GlEs(2, 0),
+			swap_interval: 1,
+		}
+	}
+
+	fn terminated(display: usize) -> usize {
+		CALLS.with(|c| c.borrow().iter()
+			.filter(|call| *call == &("eglTerminate", display))
+			.count())
+	}
+
+	#[test]
+	fn two_contexts_on_one_display() {
+		// Each test uses its own addresses, as the counts are global.
+		let mut first = display(0x1000, false);
+		let second = display(0x1000, false);
+		let other = display(0x2000, false);
+
+		assert!(first.terminate().is_ok());
+		assert!(first.is_terminated());
+		assert_eq!(terminated(0x1000), 0);
+		drop(first);
+		assert_eq!(terminated(0x1000), 0);
+
+		drop(second);
+		assert_eq!(terminated(0x1000), 1);
+		assert_eq!(CALLS.with(|c| c.borrow().iter()
+			.filter(|call| call.0 == "eglDestroyContext").count()), 2);
+
+		drop(other);
+		assert_eq!(terminated(0x2000), 1);
+
+		// Connecting again after the last one starts over.
+		drop(display(0x1000, false));
+		assert_eq!(terminated(0x1000), 2);
+	}
+
+	#[test]
+	fn driver_counts_references() {
+		let first = display(0x3000, true);
+		let second = display(0x3000, true);
+
+		drop(first);
+		assert_eq!(terminated(0x3000), 1);
+		drop(second);
+		assert_eq!(terminated(0x3000), 2);
 	}
 }
@@ -0,0 +1,1150 @@
+// Copyright Jeron A. Lau 2018.
+// Dual-licensed under either the MIT License or the Boost Software License,
+// Version 1.0.  (See accompanying file LICENSE_1_0.txt or copy at
+// https://www.boost.org/LICENSE_1_0.txt)
+
+//! A fake OpenGL ES implementation that records every call, so code using
+//! this crate can be tested without a driver.  It's OpenGL ES 2.0 unless set
+//! with `MockBackend::set_version()`.  Enabled with the `mock` feature.
+
+#![allow(non_snake_case)]
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::ffi::{ CStr, CString };
+use std::{ ptr, slice };
+use c_void;
+use { GlslType, OpenGL, Stage };
+use types::*;
+
+/// An argument of a recorded call.  Output pointers aren't recorded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Arg {
+	/// Any integer, enum or boolean argument.
+	Int(i64),
+	/// A float argument.
+	Float(f32),
+	/// A string, like shader source or a uniform's name.
+	Str(String),
+	/// An array of names, like the buffers passed to `glDeleteBuffers`.
+	Names(Vec<GLuint>),
+	/// An array of floats, like a matrix.
+	Floats(Vec<f32>),
+	/// An array of integers, like an ivec2 uniform array.
+	Ints(Vec<i64>),
+	/// A data pointer or offset.
+	Ptr(usize),
+}
+
+/// A recorded OpenGL call.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Call {
+	/// The OpenGL function, like "glBindTexture".
+	pub name: &'static str,
+	pub args: Vec<Arg>,
+}
+
+struct State {
+	calls: Vec<Call>,
+	// The last object name handed out.
+	name: GLuint,
+	// What `glGetError()` returns next.
+	errors: VecDeque<GLenum>,
+	// Functions that fail the next time they're called.
+	failures: Vec<(&'static str, GLenum)>,
+	// The index is the location.
+	locations: Vec<(GLuint, String)>,
+	version: CString,
+	extensions: CString,
+	// What `GL_CONTEXT_PROFILE_MASK` is.
+	profile_mask: GLint,
+	// The type of each shader.
+	shaders: Vec<(GLuint, GLenum)>,
+	// If shaders of each type compile, and the compile log.
+	compile: Vec<(GLenum, bool, String)>,
+	// If programs link, and the link log.
+	linked: bool,
+	link_log: String,
+	// The active attributes & uniforms of every program.
+	attributes: Vec<(String, GlslType, GLint)>,
+	uniforms: Vec<(String, GlslType, GLint)>,
+	// The uniform blocks of every program, the index is the block index.
+	blocks: Vec<String>,
+	// Programs loaded from a binary that wasn't made by the mock.
+	rejected: Vec<GLuint>,
+}
+
+impl State {
+	fn new() -> State {
+		State {
+			calls: Vec::new(),
+			name: 0,
+			errors: VecDeque::new(),
+			failures: Vec::new(),
+			locations: Vec::new(),
+			version: CString::new("OpenGL ES 2.0 Mock").unwrap(),
+			extensions: CString::default(),
+			profile_mask: 0,
+			shaders: Vec::new(),
+			compile: Vec::new(),
+			linked: true,
+			link_log: String::new(),
+			attributes: Vec::new(),
+			uniforms: Vec::new(),
+			blocks: Vec::new(),
+			rejected: Vec::new(),
+		}
+	}
+}
+
+thread_local!(static STATE: RefCell<State> = RefCell::new(State::new()));
+
+/// The mock backend for the current thread.  The state is per thread, and is
+/// reset by `MockBackend::new()`.
+pub struct MockBackend(());
+
+impl MockBackend {
+	/// Reset this thread's mock state.
+	pub fn new() -> MockBackend {
+		STATE.with(|state| *state.borrow_mut() = State::new());
+
+		MockBackend(())
+	}
+
+	/// Set the `GL_VERSION` string.  Call before `opengl()`.
+	pub fn set_version(&self, version: &str) {
+		STATE.with(|state| {
+			state.borrow_mut().version = CString::new(version)
+				.expect("Version has a nul byte");
+		});
+	}
+
+	/// Set what `GL_CONTEXT_PROFILE_MASK` is, 1 for a core profile and 2
+	/// for compatibility.  Call before `opengl()`.
+	pub fn set_profile_mask(&self, mask: i32) {
+		STATE.with(|state| state.borrow_mut().profile_mask = mask);
+	}
+
+	/// Set the `GL_EXTENSIONS` string, space-separated.  Call before
+	/// `opengl()`.
+	pub fn set_extensions(&self, extensions: &str) {
+		STATE.with(|state| {
+			state.borrow_mut().extensions = CString::new(extensions)
+				.expect("Extensions have a nul byte");
+		});
+	}
+
+	/// Create a context that uses the mock functions.
+	pub fn opengl(&self) -> OpenGL {
+		OpenGL::from_loader(MockBackend::proc_address)
+			.expect("Couldn't load the mock backend")
+	}
+
+	/// Get the address of a mock function, null if it's not implemented.
+	pub fn proc_address(name: &str) -> *const c_void {
+		match name {
+			"glGetString" => glGetString as *const c_void,
+			"glGetIntegerv" => glGetIntegerv as *const c_void,
+			"glGetError" => glGetError as *const c_void,
+			"glClear" => glClear as *const c_void,
+			"glClearColor" => glClearColor as *const c_void,
+			"glClearDepthf" => glClearDepthf as *const c_void,
+			"glClearStencil" => glClearStencil as *const c_void,
+			"glDisable" => glDisable as *const c_void,
+			"glEnable" => glEnable as *const c_void,
+			"glBlendFuncSeparate" =>
+				glBlendFuncSeparate as *const c_void,
+			"glCreateShader" => glCreateShader as *const c_void,
+			"glShaderSource" => glShaderSource as *const c_void,
+			"glCompileShader" => glCompileShader as *const c_void,
+			"glCreateProgram" => glCreateProgram as *const c_void,
+			"glAttachShader" => glAttachShader as *const c_void,
+			"glLinkProgram" => glLinkProgram as *const c_void,
+			"glGetUniformLocation" =>
+				glGetUniformLocation as *const c_void,
+			"glGenBuffers" => glGenBuffers as *const c_void,
+			"glBindBuffer" => glBindBuffer as *const c_void,
+			"glBufferData" => glBufferData as *const c_void,
+			"glGetAttribLocation" =>
+				glGetAttribLocation as *const c_void,
+			"glGetShaderiv" => glGetShaderiv as *const c_void,
+			"glGetShaderInfoLog" =>
+				glGetShaderInfoLog as *const c_void,
+			"glDrawArrays" => glDrawArrays as *const c_void,
+			"glUseProgram" => glUseProgram as *const c_void,
+			"glUniformMatrix4fv" =>
+				glUniformMatrix4fv as *const c_void,
+			"glUniform1i" => glUniform1i as *const c_void,
+			"glUniform1f" => glUniform1f as *const c_void,
+			"glUniform2f" => glUniform2f as *const c_void,
+			"glUniform3f" => glUniform3f as *const c_void,
+			"glUniform4f" => glUniform4f as *const c_void,
+			"glUniform2i" => glUniform2i as *const c_void,
+			"glUniform3i" => glUniform3i as *const c_void,
+			"glUniform4i" => glUniform4i as *const c_void,
+			"glUniformMatrix2fv" =>
+				glUniformMatrix2fv as *const c_void,
+			"glUniformMatrix3fv" =>
+				glUniformMatrix3fv as *const c_void,
+			"glUniform1fv" => glUniform1fv as *const c_void,
+			"glUniform2fv" => glUniform2fv as *const c_void,
+			"glUniform3fv" => glUniform3fv as *const c_void,
+			"glUniform4fv" => glUniform4fv as *const c_void,
+			"glUniform1iv" => glUniform1iv as *const c_void,
+			"glUniform2iv" => glUniform2iv as *const c_void,
+			"glUniform3iv" => glUniform3iv as *const c_void,
+			"glUniform4iv" => glUniform4iv as *const c_void,
+			"glBindTexture" => glBindTexture as *const c_void,
+			"glVertexAttribPointer" =>
+				glVertexAttribPointer as *const c_void,
+			"glGenTextures" => glGenTextures as *const c_void,
+			"glTexParameteri" => glTexParameteri as *const c_void,
+			"glTexImage2D" => glTexImage2D as *const c_void,
+			"glTexSubImage2D" => glTexSubImage2D as *const c_void,
+			"glEnableVertexAttribArray" =>
+				glEnableVertexAttribArray as *const c_void,
+			"glViewport" => glViewport as *const c_void,
+			"glGenerateMipmap" => glGenerateMipmap as *const c_void,
+			"glDetachShader" => glDetachShader as *const c_void,
+			"glDeleteProgram" => glDeleteProgram as *const c_void,
+			"glDeleteShader" => glDeleteShader as *const c_void,
+			"glGetProgramiv" => glGetProgramiv as *const c_void,
+			"glGetProgramInfoLog" =>
+				glGetProgramInfoLog as *const c_void,
+			"glGetActiveAttrib" => glGetActiveAttrib as *const c_void,
+			"glGetActiveUniform" => glGetActiveUniform as *const c_void,
+			"glDeleteBuffers" => glDeleteBuffers as *const c_void,
+			"glDeleteTextures" => glDeleteTextures as *const c_void,
+			"glStencilOp" => glStencilOp as *const c_void,
+			"glStencilFunc" => glStencilFunc as *const c_void,
+			"glBindAttribLocation" =>
+				glBindAttribLocation as *const c_void,
+			"glGetUniformBlockIndex" =>
+				glGetUniformBlockIndex as *const c_void,
+			"glUniformBlockBinding" =>
+				glUniformBlockBinding as *const c_void,
+			"glBindBufferBase" => glBindBufferBase as *const c_void,
+			"glProgramParameteri" =>
+				glProgramParameteri as *const c_void,
+			"glGetProgramBinary" => glGetProgramBinary as *const c_void,
+			"glProgramBinary" => glProgramBinary as *const c_void,
+			"glGetProgramBinaryOES" =>
+				glGetProgramBinaryOES as *const c_void,
+			"glProgramBinaryOES" => glProgramBinaryOES as *const c_void,
+			"glUniform1ui" => glUniform1ui as *const c_void,
+			"glUniform2ui" => glUniform2ui as *const c_void,
+			"glUniform3ui" => glUniform3ui as *const c_void,
+			"glUniform4ui" => glUniform4ui as *const c_void,
+			"glUniform1uiv" => glUniform1uiv as *const c_void,
+			"glUniform2uiv" => glUniform2uiv as *const c_void,
+			"glUniform3uiv" => glUniform3uiv as *const c_void,
+			"glUniform4uiv" => glUniform4uiv as *const c_void,
+			"glGenVertexArrays" => glGenVertexArrays as *const c_void,
+			"glBindVertexArray" => glBindVertexArray as *const c_void,
+			"glDeleteVertexArrays" =>
+				glDeleteVertexArrays as *const c_void,
+			"glDrawArraysInstanced" =>
+				glDrawArraysInstanced as *const c_void,
+			"glVertexAttribDivisor" =>
+				glVertexAttribDivisor as *const c_void,
+			_ => ptr::null(),
+		}
+	}
+
+	/// Get the calls made so far, oldest first.  `glGetError()` and
+	/// `glGetString()` aren't recorded.
+	pub fn calls(&self) -> Vec<Call> {
+		STATE.with(|state| state.borrow().calls.clone())
+	}
+
+	/// Get the calls made so far, and forget them.
+	pub fn take_calls(&self) -> Vec<Call> {
+		STATE.with(|state| state.borrow_mut().calls.split_off(0))
+	}
+
+	/// Get the names of the functions called so far, oldest first.
+	pub fn names(&self) -> Vec<&'static str> {
+		STATE.with(|state| {
+			state.borrow().calls.iter().map(|call| call.name)
+				.collect()
+		})
+	}
+
+	/// Make `glGetError()` return `error` after the next call to `name`
+	/// (like "glBindTexture").
+	pub fn fail(&self, name: &'static str, error: GLenum) {
+		STATE.with(|state| {
+			state.borrow_mut().failures.push((name, error));
+		});
+	}
+
+	/// Set if `stage` shaders compiled from now on succeed, and their info
+	/// log.  Panics for `Stage::Link`, use `set_link_result()`.
+	pub fn set_compile_result(&self, stage: Stage, compiled: bool, log: &str)
+	{
+		let kind = stage.gl_enum();
+
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			state.compile.retain(|c| c.0 != kind);
+			state.compile.push((kind, compiled, log.to_string()));
+		});
+	}
+
+	/// Set if programs linked from now on succeed, and their info log.
+	pub fn set_link_result(&self, linked: bool, log: &str) {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			state.linked = linked;
+			state.link_log = log.to_string();
+		});
+	}
+
+	/// Set the active attributes of programs, as the name, type and array
+	/// size.
+	pub fn set_attributes(&self, attributes: &[(&str, GlslType, i32)]) {
+		STATE.with(|state| {
+			state.borrow_mut().attributes = attributes.iter()
+				.map(|a| (a.0.to_string(), a.1, a.2)).collect();
+		});
+	}
+
+	/// Set the active uniforms of programs, as the name, type and array
+	/// size.
+	pub fn set_uniforms(&self, uniforms: &[(&str, GlslType, i32)]) {
+		STATE.with(|state| {
+			state.borrow_mut().uniforms = uniforms.iter()
+				.map(|u| (u.0.to_string(), u.1, u.2)).collect();
+		});
+	}
+
+	/// Set the uniform blocks of programs.  Set the version to OpenGL ES
+	/// 3.0 to use them.
+	pub fn set_uniform_blocks(&self, blocks: &[&str]) {
+		STATE.with(|state| {
+			state.borrow_mut().blocks = blocks.iter()
+				.map(|b| b.to_string()).collect();
+		});
+	}
+
+	/// Make the next `glGetError()` return `error`.
+	pub fn push_error(&self, error: GLenum) {
+		STATE.with(|state| state.borrow_mut().errors.push_back(error));
+	}
+}
+
+impl Default for MockBackend {
+	fn default() -> MockBackend {
+		MockBackend::new()
+	}
+}
+
+/// Record a call, and queue its scripted error if it has one.
+fn record(name: &'static str, args: Vec<Arg>) {
+	STATE.with(|state| {
+		let mut state = state.borrow_mut();
+
+		if let Some(i) = state.failures.iter().position(|f| f.0 == name)
+		{
+			let (_, error) = state.failures.remove(i);
+			state.errors.push_back(error);
+		}
+		state.calls.push(Call { name, args });
+	})
+}
+
+/// Hand out a new object name.
+fn gen_name() -> GLuint {
+	STATE.with(|state| {
+		let mut state = state.borrow_mut();
+
+		state.name += 1;
+		state.name
+	})
+}
+
+/// Get if `shader` compiles, and its log.
+fn compile_result(shader: GLuint) -> (bool, String) {
+	STATE.with(|state| {
+		let state = state.borrow();
+		let kind = state.shaders.iter().find(|s| s.0 == shader)
+			.map(|s| s.1);
+
+		state.compile.iter().find(|c| Some(c.0) == kind)
+			.map(|c| (c.1, c.2.clone()))
+			.unwrap_or((true, String::new()))
+	})
+}
+
+/// Get the fake location of `name` in `program`.
+fn location(program: GLuint, name: *const GLchar) -> (String, GLint) {
+	let name = unsafe { CStr::from_ptr(name) }.to_string_lossy()
+		.into_owned();
+	let location = STATE.with(|state| {
+		let mut state = state.borrow_mut();
+		let key = (program, name.clone());
+
+		match state.locations.iter().position(|l| *l == key) {
+			Some(i) => i,
+			None => {
+				state.locations.push(key);
+				state.locations.len() - 1
+			}
+		}
+	});
+
+	(name, location as GLint)
+}
+
+unsafe fn names(n: GLsizei, names: *const GLuint) -> Vec<GLuint> {
+	slice::from_raw_parts(names, n as usize).to_vec()
+}
+
+unsafe fn gen_names(n: GLsizei, names: *mut GLuint) -> Vec<GLuint> {
+	let names = slice::from_raw_parts_mut(names, n as usize);
+
+	for name in names.iter_mut() {
+		*name = gen_name();
+	}
+	names.to_vec()
+}
+
+fn int<T: Into<i64>>(value: T) -> Arg {
+	Arg::Int(value.into())
+}
+
+unsafe fn floats(value: *const GLfloat, n: GLsizei) -> Arg {
+	Arg::Floats(slice::from_raw_parts(value, n as usize).to_vec())
+}
+
+unsafe fn ints(value: *const GLint, n: GLsizei) -> Arg {
+	Arg::Ints(slice::from_raw_parts(value, n as usize).iter()
+		.map(|&i| i.into()).collect())
+}
+
+unsafe fn uints(value: *const GLuint, n: GLsizei) -> Arg {
+	Arg::Ints(slice::from_raw_parts(value, n as usize).iter()
+		.map(|&i| i.into()).collect())
+}
+
+unsafe extern "system" fn glGetString(name: GLenum) -> *const GLubyte {
+	match name {
+		GL_VERSION => STATE.with(|state| {
+			state.borrow().version.as_ptr() as *const _
+		}),
+		GL_EXTENSIONS => STATE.with(|state| {
+			state.borrow().extensions.as_ptr() as *const _
+		}),
+		GL_RENDERER => b"Mock\0".as_ptr(),
+		_ => ptr::null(),
+	}
+}
+
+unsafe extern "system" fn glGetIntegerv(name: GLenum, value: *mut GLint) {
+	if name == GL_CONTEXT_PROFILE_MASK {
+		*value = STATE.with(|state| state.borrow().profile_mask);
+	}
+	record("glGetIntegerv", vec![int(name)]);
+}
+
+unsafe extern "system" fn glGetError() -> GLenum {
+	STATE.with(|state| state.borrow_mut().errors.pop_front().unwrap_or(0))
+}
+
+unsafe extern "system" fn glClear(mask: GLbitfield) {
+	record("glClear", vec![int(mask)]);
+}
+
+unsafe extern "system" fn glClearColor(r: GLfloat, g: GLfloat, b: GLfloat,
+	a: GLfloat)
+{
+	record("glClearColor", vec![Arg::Float(r), Arg::Float(g),
+		Arg::Float(b), Arg::Float(a)]);
+}
+
+unsafe extern "system" fn glClearDepthf(depth: GLfloat) {
+	record("glClearDepthf", vec![Arg::Float(depth)]);
+}
+
+unsafe extern "system" fn glClearStencil(stencil: GLint) {
+	record("glClearStencil", vec![int(stencil)]);
+}
+
+unsafe extern "system" fn glDisable(cap: GLenum) {
+	record("glDisable", vec![int(cap)]);
+}
+
+unsafe extern "system" fn glEnable(cap: GLenum) {
+	record("glEnable", vec![int(cap)]);
+}
+
+unsafe extern "system" fn glBlendFuncSeparate(a: GLenum, b: GLenum,
+	c: GLenum, d: GLenum)
+{
+	record("glBlendFuncSeparate", vec![int(a), int(b), int(c), int(d)]);
+}
+
+unsafe extern "system" fn glCreateShader(kind: GLenum) -> GLuint {
+	let shader = gen_name();
+
+	STATE.with(|state| state.borrow_mut().shaders.push((shader, kind)));
+	record("glCreateShader", vec![int(kind)]);
+	shader
+}
+
+unsafe extern "system" fn glShaderSource(shader: GLuint, count: GLsizei,
+	strings: *const *const GLchar, lengths: *const GLint)
+{
+	let mut source = String::new();
+
+	for i in 0..count as usize {
+		let string = *strings.add(i);
+		let bytes = if lengths.is_null() || *lengths.add(i) < 0 {
+			CStr::from_ptr(string).to_bytes()
+		} else {
+			slice::from_raw_parts(string as *const u8,
+				*lengths.add(i) as usize)
+		};
+
+		source.push_str(&String::from_utf8_lossy(bytes));
+	}
+
+	record("glShaderSource", vec![int(shader), int(count),
+		Arg::Str(source)]);
+}
+
+unsafe extern "system" fn glCompileShader(shader: GLuint) {
+	record("glCompileShader", vec![int(shader)]);
+}
+
+unsafe extern "system" fn glCreateProgram() -> GLuint {
+	let program = gen_name();
+
+	record("glCreateProgram", vec![]);
+	program
+}
+
+unsafe extern "system" fn glAttachShader(program: GLuint, shader: GLuint) {
+	record("glAttachShader", vec![int(program), int(shader)]);
+}
+
+unsafe extern "system" fn glLinkProgram(program: GLuint) {
+	record("glLinkProgram", vec![int(program)]);
+}
+
+unsafe extern "system" fn glGetUniformLocation(program: GLuint,
+	name: *const GLchar) -> GLint
+{
+	let (name, location) = location(program, name);
+
+	record("glGetUniformLocation", vec![int(program), Arg::Str(name)]);
+	location
+}
+
+unsafe extern "system" fn glGenBuffers(n: GLsizei, buffers: *mut GLuint) {
+	let buffers = gen_names(n, buffers);
+
+	record("glGenBuffers", vec![int(n), Arg::Names(buffers)]);
+}
+
+unsafe extern "system" fn glBindBuffer(target: GLenum, buffer: GLuint) {
+	record("glBindBuffer", vec![int(target), int(buffer)]);
+}
+
+unsafe extern "system" fn glBufferData(target: GLenum, size: GLsizeiptr,
+	data: *const c_void, usage: GLenum)
+{
+	record("glBufferData", vec![int(target), Arg::Int(size as i64),
+		Arg::Ptr(data as usize), int(usage)]);
+}
+
+unsafe extern "system" fn glGetAttribLocation(program: GLuint,
+	name: *const GLchar) -> GLint
+{
+	let (name, location) = location(program, name);
+
+	record("glGetAttribLocation", vec![int(program), Arg::Str(name)]);
+	location
+}
+
+unsafe extern "system" fn glGetShaderiv(shader: GLuint, pname: GLenum,
+	params: *mut GLint)
+{
+	let (compiled, log) = compile_result(shader);
+
+	*params = match pname {
+		GL_COMPILE_STATUS => compiled as GLint,
+		GL_INFO_LOG_LENGTH if log.is_empty() => 0,
+		GL_INFO_LOG_LENGTH => log.len() as GLint + 1,
+		_ => 0,
+	};
+	record("glGetShaderiv", vec![int(shader), int(pname)]);
+}
+
+unsafe extern "system" fn glGetShaderInfoLog(shader: GLuint,
+	max_length: GLsizei, length: *mut GLsizei, log: *mut GLchar)
+{
+	write_log(&compile_result(shader).1, max_length, length, log);
+	record("glGetShaderInfoLog", vec![int(shader), int(max_length)]);
+}
+
+unsafe extern "system" fn glDrawArrays(mode: GLenum, first: GLint,
+	count: GLsizei)
+{
+	record("glDrawArrays", vec![int(mode), int(first), int(count)]);
+}
+
+unsafe extern "system" fn glUseProgram(program: GLuint) {
+	record("glUseProgram", vec![int(program)]);
+}
+
+unsafe extern "system" fn glUniformMatrix2fv(location: GLint,
+	count: GLsizei, transpose: GLboolean, value: *const GLfloat)
+{
+	record("glUniformMatrix2fv", vec![int(location), int(count),
+		int(transpose), floats(value, 4 * count)]);
+}
+
+unsafe extern "system" fn glUniformMatrix3fv(location: GLint,
+	count: GLsizei, transpose: GLboolean, value: *const GLfloat)
+{
+	record("glUniformMatrix3fv", vec![int(location), int(count),
+		int(transpose), floats(value, 9 * count)]);
+}
+
+unsafe extern "system" fn glUniformMatrix4fv(location: GLint,
+	count: GLsizei, transpose: GLboolean, value: *const GLfloat)
+{
+	record("glUniformMatrix4fv", vec![int(location), int(count),
+		int(transpose), floats(value, 16 * count)]);
+}
+
+unsafe extern "system" fn glUniform1i(location: GLint, x: GLint) {
+	record("glUniform1i", vec![int(location), int(x)]);
+}
+
+unsafe extern "system" fn glUniform2i(location: GLint, x: GLint, y: GLint) {
+	record("glUniform2i", vec![int(location), int(x), int(y)]);
+}
+
+unsafe extern "system" fn glUniform3i(location: GLint, x: GLint, y: GLint,
+	z: GLint)
+{
+	record("glUniform3i", vec![int(location), int(x), int(y), int(z)]);
+}
+
+unsafe extern "system" fn glUniform4i(location: GLint, x: GLint, y: GLint,
+	z: GLint, w: GLint)
+{
+	record("glUniform4i", vec![int(location), int(x), int(y), int(z),
+		int(w)]);
+}
+
+unsafe extern "system" fn glUniform1f(location: GLint, x: GLfloat) {
+	record("glUniform1f", vec![int(location), Arg::Float(x)]);
+}
+
+unsafe extern "system" fn glUniform2f(location: GLint, x: GLfloat,
+	y: GLfloat)
+{
+	record("glUniform2f", vec![int(location), Arg::Float(x),
+		Arg::Float(y)]);
+}
+
+unsafe extern "system" fn glUniform3f(location: GLint, x: GLfloat,
+	y: GLfloat, z: GLfloat)
+{
+	record("glUniform3f", vec![int(location), Arg::Float(x),
+		Arg::Float(y), Arg::Float(z)]);
+}
+
+unsafe extern "system" fn glUniform4f(location: GLint, x: GLfloat,
+	y: GLfloat, z: GLfloat, w: GLfloat)
+{
+	record("glUniform4f", vec![int(location), Arg::Float(x),
+		Arg::Float(y), Arg::Float(z), Arg::Float(w)]);
+}
+
+unsafe extern "system" fn glUniform1fv(location: GLint, count: GLsizei,
+	value: *const GLfloat)
+{
+	record("glUniform1fv", vec![int(location), int(count),
+		floats(value, count)]);
+}
+
+unsafe extern "system" fn glUniform2fv(location: GLint, count: GLsizei,
+	value: *const GLfloat)
+{
+	record("glUniform2fv", vec![int(location), int(count),
+		floats(value, 2 * count)]);
+}
+
+unsafe extern "system" fn glUniform3fv(location: GLint, count: GLsizei,
+	value: *const GLfloat)
+{
+	record("glUniform3fv", vec![int(location), int(count),
+		floats(value, 3 * count)]);
+}
+
+unsafe extern "system" fn glUniform4fv(location: GLint, count: GLsizei,
+	value: *const GLfloat)
+{
+	record("glUniform4fv", vec![int(location), int(count),
+		floats(value, 4 * count)]);
+}
+
+unsafe extern "system" fn glUniform1iv(location: GLint, count: GLsizei,
+	value: *const GLint)
+{
+	record("glUniform1iv", vec![int(location), int(count),
+		ints(value, count)]);
+}
+
+unsafe extern "system" fn glUniform2iv(location: GLint, count: GLsizei,
+	value: *const GLint)
+{
+	record("glUniform2iv", vec![int(location), int(count),
+		ints(value, 2 * count)]);
+}
+
+unsafe extern "system" fn glUniform3iv(location: GLint, count: GLsizei,
+	value: *const GLint)
+{
+	record("glUniform3iv", vec![int(location), int(count),
+		ints(value, 3 * count)]);
+}
+
+unsafe extern "system" fn glUniform4iv(location: GLint, count: GLsizei,
+	value: *const GLint)
+{
+	record("glUniform4iv", vec![int(location), int(count),
+		ints(value, 4 * count)]);
+}
+
+unsafe extern "system" fn glUniform1ui(location: GLint, x: GLuint) {
+	record("glUniform1ui", vec![int(location), int(x)]);
+}
+
+unsafe extern "system" fn glUniform2ui(location: GLint, x: GLuint, y: GLuint)
+{
+	record("glUniform2ui", vec![int(location), int(x), int(y)]);
+}
+
+unsafe extern "system" fn glUniform3ui(location: GLint, x: GLuint, y: GLuint,
+	z: GLuint)
+{
+	record("glUniform3ui", vec![int(location), int(x), int(y), int(z)]);
+}
+
+unsafe extern "system" fn glUniform4ui(location: GLint, x: GLuint, y: GLuint,
+	z: GLuint, w: GLuint)
+{
+	record("glUniform4ui", vec![int(location), int(x), int(y), int(z),
+		int(w)]);
+}
+
+unsafe extern "system" fn glUniform1uiv(location: GLint, count: GLsizei,
+	value: *const GLuint)
+{
+	record("glUniform1uiv", vec![int(location), int(count),
+		uints(value, count)]);
+}
+
+unsafe extern "system" fn glUniform2uiv(location: GLint, count: GLsizei,
+	value: *const GLuint)
+{
+	record("glUniform2uiv", vec![int(location), int(count),
+		uints(value, 2 * count)]);
+}
+
+unsafe extern "system" fn glUniform3uiv(location: GLint, count: GLsizei,
+	value: *const GLuint)
+{
+	record("glUniform3uiv", vec![int(location), int(count),
+		uints(value, 3 * count)]);
+}
+
+unsafe extern "system" fn glUniform4uiv(location: GLint, count: GLsizei,
+	value: *const GLuint)
+{
+	record("glUniform4uiv", vec![int(location), int(count),
+		uints(value, 4 * count)]);
+}
+
+unsafe extern "system" fn glBindTexture(target: GLenum, texture: GLuint) {
+	record("glBindTexture", vec![int(target), int(texture)]);
+}
+
+unsafe extern "system" fn glVertexAttribPointer(index: GLuint, size: GLint,
+	kind: GLenum, normalized: GLboolean, stride: GLsizei,
+	offset: *const c_void)
+{
+	record("glVertexAttribPointer", vec![int(index), int(size), int(kind),
+		int(normalized), int(stride), Arg::Ptr(offset as usize)]);
+}
+
+unsafe extern "system" fn glGenTextures(n: GLsizei, textures: *mut GLuint) {
+	let textures = gen_names(n, textures);
+
+	record("glGenTextures", vec![int(n), Arg::Names(textures)]);
+}
+
+unsafe extern "system" fn glTexParameteri(target: GLenum, pname: GLenum,
+	param: GLint)
+{
+	record("glTexParameteri", vec![int(target), int(pname), int(param)]);
+}
+
+unsafe extern "system" fn glTexImage2D(target: GLenum, level: GLint,
+	internal: GLint, w: GLsizei, h: GLsizei, border: GLint,
+	format: GLenum, kind: GLenum, pixels: *const c_void)
+{
+	record("glTexImage2D", vec![int(target), int(level), int(internal),
+		int(w), int(h), int(border), int(format), int(kind),
+		Arg::Ptr(pixels as usize)]);
+}
+
+unsafe extern "system" fn glTexSubImage2D(target: GLenum, level: GLint,
+	x: GLint, y: GLint, w: GLsizei, h: GLsizei, format: GLenum,
+	kind: GLenum, pixels: *const c_void)
+{
+	record("glTexSubImage2D", vec![int(target), int(level), int(x),
+		int(y), int(w), int(h), int(format), int(kind),
+		Arg::Ptr(pixels as usize)]);
+}
+
+unsafe extern "system" fn glEnableVertexAttribArray(index: GLuint) {
+	record("glEnableVertexAttribArray", vec![int(index)]);
+}
+
+unsafe extern "system" fn glViewport(x: GLint, y: GLint, w: GLsizei,
+	h: GLsizei)
+{
+	record("glViewport", vec![int(x), int(y), int(w), int(h)]);
+}
+
+unsafe extern "system" fn glGenerateMipmap(target: GLenum) {
+	record("glGenerateMipmap", vec![int(target)]);
+}
+
+unsafe extern "system" fn glDetachShader(program: GLuint, shader: GLuint) {
+	record("glDetachShader", vec![int(program), int(shader)]);
+}
+
+unsafe extern "system" fn glDeleteProgram(program: GLuint) {
+	record("glDeleteProgram", vec![int(program)]);
+}
+
+unsafe extern "system" fn glDeleteShader(shader: GLuint) {
+	record("glDeleteShader", vec![int(shader)]);
+}
+
+unsafe extern "system" fn glGetProgramiv(program: GLuint, pname: GLenum,
+	params: *mut GLint)
+{
+	*params = STATE.with(|state| {
+		let state = state.borrow();
+
+		match pname {
+			GL_LINK_STATUS if state.rejected.contains(&program) => 0,
+			GL_LINK_STATUS => state.linked as GLint,
+			GL_INFO_LOG_LENGTH if state.link_log.is_empty() => 0,
+			GL_INFO_LOG_LENGTH => state.link_log.len() as GLint + 1,
+			GL_ACTIVE_ATTRIBUTES => state.attributes.len() as GLint,
+			GL_ACTIVE_UNIFORMS => state.uniforms.len() as GLint,
+			GL_ACTIVE_ATTRIBUTE_MAX_LENGTH => max_length(&state.attributes),
+			GL_ACTIVE_UNIFORM_MAX_LENGTH => max_length(&state.uniforms),
+			GL_PROGRAM_BINARY_LENGTH => BINARY.len() as GLint,
+			_ => 0,
+		}
+	});
+	record("glGetProgramiv", vec![int(program), int(pname)]);
+}
+
+unsafe extern "system" fn glGetProgramInfoLog(program: GLuint,
+	max_length: GLsizei, length: *mut GLsizei, log: *mut GLchar)
+{
+	STATE.with(|state| write_log(&state.borrow().link_log, max_length,
+		length, log));
+	record("glGetProgramInfoLog", vec![int(program), int(max_length)]);
+}
+
+/// The length of the longest name, plus the nul.
+fn max_length(variables: &[(String, GlslType, GLint)]) -> GLint {
+	variables.iter().map(|v| v.0.len() as GLint + 1).max().unwrap_or(0)
+}
+
+unsafe extern "system" fn glGetActiveAttrib(program: GLuint, index: GLuint,
+	max_length: GLsizei, length: *mut GLsizei, size: *mut GLint,
+	kind: *mut GLenum, name: *mut GLchar)
+{
+	STATE.with(|state| {
+		let state = state.borrow();
+		let attribute = &state.attributes[index as usize];
+
+		*size = attribute.2;
+		*kind = attribute.1.gl_enum();
+		write_log(&attribute.0, max_length, length, name);
+	});
+	record("glGetActiveAttrib", vec![int(program), int(index),
+		int(max_length)]);
+}
+
+unsafe extern "system" fn glGetActiveUniform(program: GLuint, index: GLuint,
+	max_length: GLsizei, length: *mut GLsizei, size: *mut GLint,
+	kind: *mut GLenum, name: *mut GLchar)
+{
+	STATE.with(|state| {
+		let state = state.borrow();
+		let uniform = &state.uniforms[index as usize];
+
+		*size = uniform.2;
+		*kind = uniform.1.gl_enum();
+		write_log(&uniform.0, max_length, length, name);
+	});
+	record("glGetActiveUniform", vec![int(program), int(index),
+		int(max_length)]);
+}
+
+/// Copy a string into the output of `glGet*InfoLog()` or `glGetActive*()`.
+unsafe fn write_log(text: &str, max_length: GLsizei, length: *mut GLsizei,
+	log: *mut GLchar)
+{
+	if max_length <= 0 {
+		return;
+	}
+
+	let count = text.len().min(max_length as usize - 1);
+
+	ptr::copy_nonoverlapping(text.as_ptr() as *const GLchar, log, count);
+	*log.add(count) = 0;
+	if !length.is_null() {
+		*length = count as GLsizei;
+	}
+}
+
+unsafe extern "system" fn glDeleteBuffers(n: GLsizei, buffers: *const GLuint) {
+	record("glDeleteBuffers", vec![int(n), Arg::Names(names(n, buffers))]);
+}
+
+unsafe extern "system" fn glDeleteTextures(n: GLsizei, textures: *const GLuint)
+{
+	record("glDeleteTextures", vec![int(n),
+		Arg::Names(names(n, textures))]);
+}
+
+unsafe extern "system" fn glStencilOp(fail: GLenum, zfail: GLenum,
+	zpass: GLenum)
+{
+	record("glStencilOp", vec![int(fail), int(zfail), int(zpass)]);
+}
+
+unsafe extern "system" fn glStencilFunc(func: GLenum, reference: GLint,
+	mask: GLuint)
+{
+	record("glStencilFunc", vec![int(func), int(reference), int(mask)]);
+}
+
+unsafe extern "system" fn glBindAttribLocation(program: GLuint, index: GLuint,
+	name: *const GLchar)
+{
+	let name = CStr::from_ptr(name).to_string_lossy().into_owned();
+
+	record("glBindAttribLocation", vec![int(program), int(index),
+		Arg::Str(name)]);
+}
+
+unsafe extern "system" fn glGetUniformBlockIndex(program: GLuint,
+	name: *const GLchar) -> GLuint
+{
+	let name = CStr::from_ptr(name).to_string_lossy().into_owned();
+	let index = STATE.with(|state| {
+		state.borrow().blocks.iter().position(|b| *b == name)
+			.map(|i| i as GLuint).unwrap_or(GL_INVALID_INDEX)
+	});
+
+	record("glGetUniformBlockIndex", vec![int(program), Arg::Str(name)]);
+	index
+}
+
+unsafe extern "system" fn glUniformBlockBinding(program: GLuint,
+	index: GLuint, binding: GLuint)
+{
+	record("glUniformBlockBinding", vec![int(program), int(index),
+		int(binding)]);
+}
+
+unsafe extern "system" fn glBindBufferBase(target: GLenum, index: GLuint,
+	buffer: GLuint)
+{
+	record("glBindBufferBase", vec![int(target), int(index), int(buffer)]);
+}
+
+/// The binary of every program, and its format.
+const BINARY: &[u8] = b"mock program";
+const BINARY_FORMAT: GLenum = 1;
+
+unsafe extern "system" fn glProgramParameteri(program: GLuint, pname: GLenum,
+	value: GLint)
+{
+	record("glProgramParameteri", vec![int(program), int(pname),
+		int(value)]);
+}
+
+unsafe extern "system" fn glGetProgramBinary(program: GLuint,
+	max_length: GLsizei, length: *mut GLsizei, format: *mut GLenum,
+	binary: *mut c_void)
+{
+	get_binary("glGetProgramBinary", program, max_length, length, format,
+		binary);
+}
+
+unsafe extern "system" fn glGetProgramBinaryOES(program: GLuint,
+	max_length: GLsizei, length: *mut GLsizei, format: *mut GLenum,
+	binary: *mut c_void)
+{
+	get_binary("glGetProgramBinaryOES", program, max_length, length,
+		format, binary);
+}
+
+unsafe fn get_binary(name: &'static str, program: GLuint,
+	max_length: GLsizei, length: *mut GLsizei, format: *mut GLenum,
+	binary: *mut c_void)
+{
+	let count = BINARY.len().min(max_length.max(0) as usize);
+
+	ptr::copy_nonoverlapping(BINARY.as_ptr(), binary as *mut u8, count);
+	if !length.is_null() {
+		*length = count as GLsizei;
+	}
+	*format = BINARY_FORMAT;
+	record(name, vec![int(program), int(max_length)]);
+}
+
+unsafe extern "system" fn glProgramBinary(program: GLuint, format: GLenum,
+	binary: *const c_void, length: GLsizei)
+{
+	load_binary("glProgramBinary", program, format, binary, length);
+}
+
+unsafe extern "system" fn glProgramBinaryOES(program: GLuint,
+	format: GLenum, binary: *const c_void, length: GLsizei)
+{
+	load_binary("glProgramBinaryOES", program, format, binary, length);
+}
+
+unsafe fn load_binary(name: &'static str, program: GLuint, format: GLenum,
+	binary: *const c_void, length: GLsizei)
+{
+	let binary = slice::from_raw_parts(binary as *const u8,
+		length as usize);
+
+	if format != BINARY_FORMAT || binary != BINARY {
+		STATE.with(|state| state.borrow_mut().rejected.push(program));
+	}
+	record(name, vec![int(program), int(format), int(length)]);
+}
+
+unsafe extern "system" fn glGenVertexArrays(n: GLsizei, arrays: *mut GLuint) {
+	let arrays = gen_names(n, arrays);
+
+	record("glGenVertexArrays", vec![int(n), Arg::Names(arrays)]);
+}
+
+unsafe extern "system" fn glBindVertexArray(array: GLuint) {
+	record("glBindVertexArray", vec![int(array)]);
+}
+
+unsafe extern "system" fn glDeleteVertexArrays(n: GLsizei,
+	arrays: *const GLuint)
+{
+	record("glDeleteVertexArrays", vec![int(n),
+		Arg::Names(names(n, arrays))]);
+}
+
+unsafe extern "system" fn glDrawArraysInstanced(mode: GLenum, first: GLint,
+	count: GLsizei, instances: GLsizei)
+{
+	record("glDrawArraysInstanced", vec![int(mode), int(first), int(count),
+		int(instances)]);
+}
+
+unsafe extern "system" fn glVertexAttribDivisor(index: GLuint,
+	divisor: GLuint)
+{
+	record("glVertexAttribDivisor", vec![int(index), int(divisor)]);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use { Capability, Profile, Version };
+
+	#[test]
+	fn records_calls() {
+		let mock = MockBackend::new();
+		let opengl = mock.opengl();
+
+		opengl.viewport(640, 480);
+		assert_eq!(mock.calls(), vec![Call {
+			name: "glViewport",
+			args: vec![int(0), int(0), int(640), int(480)],
+		}]);
+		assert!(mock.take_calls().len() == 1 && mock.calls().is_empty());
+	}
+
+	#[test]
+	#[cfg(any(debug_assertions, feature = "checked"))]
+	fn scripted_errors() {
+		let mock = MockBackend::new();
+		let opengl = mock.opengl();
+
+		opengl.set_error_policy(::ErrorPolicy::Collect);
+		mock.fail("glViewport", 0x0501);
+		opengl.viewport(1, 1);
+		opengl.viewport(1, 1);
+
+		let errors = opengl.errors();
+		assert_eq!(errors.len(), 1);
+		assert_eq!(errors[0].code, ::ErrorCode::InvalidValue);
+		assert_eq!(errors[0].function, "glViewport");
+	}
+
+	#[test]
+	fn capabilities() {
+		let mock = MockBackend::new();
+		assert!(!mock.opengl().has(Capability::VertexArray));
+
+		mock.set_version("OpenGL ES 3.0 Mock");
+		let opengl = mock.opengl();
+
+		for &capability in &[Capability::VertexArray,
+			Capability::Instancing, Capability::UniformBuffer,
+			Capability::ProgramBinary, Capability::UnsignedUniform]
+		{
+			assert!(opengl.has(capability), "{:?}", capability);
+		}
+	}
+
+	#[test]
+	fn core_profile_from_mask() {
+		let mock = MockBackend::new();
+		mock.set_version("4.6.0 NVIDIA 535.54.03");
+		mock.set_profile_mask(1);
+		let opengl = mock.opengl();
+
+		assert_eq!(opengl.version(), Version::Gl(4, 6, Profile::Core));
+		assert!(mock.names().contains(&"glGenVertexArrays"));
+		assert!(mock.names().contains(&"glBindVertexArray"));
+
+		let mock = MockBackend::new();
+		mock.set_version("4.6.0 NVIDIA 535.54.03");
+		mock.set_profile_mask(2);
+
+		assert_eq!(mock.opengl().version(),
+			Version::Gl(4, 6, Profile::Compatibility));
+		assert!(!mock.names().contains(&"glGenVertexArrays"));
+	}
+}
@@ -0,0 +1,451 @@
+// Copyright Jeron A. Lau 2018.
+// Dual-licensed under either the MIT License or the Boost Software License,
+// Version 1.0.  (See accompanying file LICENSE_1_0.txt or copy at
+// https://www.boost.org/LICENSE_1_0.txt)
+
+use std::{ fs, path::{ Path, PathBuf } };
+use { Diagnostic, PreprocessError };
+
+/// Finds the files of `#include "file"` directives.
+pub trait Include {
+	/// Get the name and source of the file `path`, included from the file
+	/// named `from`.  The name is used for diagnostics and later includes.
+	fn resolve(&mut self, path: &str, from: &str) -> Option<(String, String)>;
+}
+
+/// Includes from a closure that gets the source of a path.
+impl<F: FnMut(&str) -> Option<String>> Include for F {
+	fn resolve(&mut self, path: &str, _: &str) -> Option<(String, String)> {
+		Some((path.to_string(), self(path)?))
+	}
+}
+
+/// Includes files from disk, relative to the including file, then to each of
+/// the search directories.
+#[derive(Clone, Debug, Default)]
+pub struct FileInclude {
+	dirs: Vec<PathBuf>,
+}
+
+impl FileInclude {
+	/// Include relative to the including file only.
+	pub fn new() -> Self {
+		FileInclude { dirs: Vec::new() }
+	}
+
+	/// Also search `dir` for included files.
+	pub fn dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+		self.dirs.push(dir.into());
+		self
+	}
+}
+
+impl Include for FileInclude {
+	fn resolve(&mut self, path: &str, from: &str) -> Option<(String, String)> {
+		let relative = Path::new(from).parent().map(|dir| dir.join(path));
+
+		relative.into_iter()
+			.chain(self.dirs.iter().map(|dir| dir.join(path)))
+			.find_map(|path| {
+				let source = fs::read_to_string(&path).ok()?;
+
+				Some((path.to_string_lossy().into_owned(), source))
+			})
+	}
+}
+
+/// Prepares shader source for `Program::try_from_sources()`: resolves
+/// `#include "file"`, adds `#define`s and moves `#version`, `#extension` and
+/// `precision` lines to the top.  Lines inside `#if`, `#ifdef` and `#ifndef`
+/// stay in place, and includes that can't be found are left for the driver
+/// there.
+#[derive(Default)]
+pub struct Preprocessor<'a> {
+	defines: Vec<(String, String)>,
+	include: Option<Box<dyn Include + 'a>>,
+}
+
+impl<'a> Preprocessor<'a> {
+	/// A preprocessor without defines, that fails on `#include`.
+	pub fn new() -> Self {
+		Preprocessor { defines: Vec::new(), include: None }
+	}
+
+	/// Add `#define name value` after the `#version`.
+	pub fn define(mut self, name: &str, value: &str) -> Self {
+		self.defines.push((name.to_string(), value.to_string()));
+		self
+	}
+
+	/// Find included files with `include`.
+	pub fn include<I: Include + 'a>(mut self, include: I) -> Self {
+		self.include = Some(Box::new(include));
+		self
+	}
+
+	/// Preprocess `source`, from the file `name`.
+	pub fn process(&mut self, name: &str, source: &[u8])
+		-> Result<ShaderSource, PreprocessError>
+	{
+		let mut output = Output::default();
+
+		output.files.push(name.to_string());
+		self.expand(0, &String::from_utf8_lossy(source), &mut vec![0],
+			&mut output)?;
+
+		let defines = self.defines.iter().map(|(name, value)| {
+			(format!("#define {} {}", name, value), None)
+		});
+		let lines: Vec<Line> = output.version.into_iter()
+			.chain(output.extensions)
+			.chain(defines)
+			.chain(output.precisions)
+			.chain(output.body)
+			.collect();
+		let mut code = String::new();
+
+		for line in &lines {
+			code.push_str(&line.0);
+			code.push('\n');
+		}
+
+		Ok(ShaderSource {
+			code,
+			files: output.files,
+			lines: lines.into_iter().map(|line| line.1).collect(),
+		})
+	}
+
+	/// Add the lines of `text`, from `files[file]`, to `output`.  `stack`
+	/// is the files being included, to find include cycles.
+	fn expand(&mut self, file: usize, text: &str, stack: &mut Vec<usize>,
+		output: &mut Output) -> Result<(), PreprocessError>
+	{
+		let name = output.files[file].clone();
+
+		for (i, line) in text.lines().enumerate() {
+			let origin = Some((file, i as u32 + 1));
+			let error = |message: String| PreprocessError {
+				file: name.clone(),
+				line: Some(i as u32 + 1),
+				message,
+			};
+			let trimmed = line.trim();
+			let conditions = &mut output.conditions;
+
+			match directive(trimmed) {
+				Some(("if", rest)) => conditions.push(
+					Condition::new(literal(rest))),
+				Some(("ifdef", _)) | Some(("ifndef", _)) =>
+					conditions.push(Condition::new(None)),
+				Some(("elif", rest)) => if let Some(c) =
+					conditions.last_mut()
+				{
+					c.elif(literal(rest));
+				},
+				Some(("else", _)) => if let Some(c) =
+					conditions.last_mut()
+				{
+					c.otherwise();
+				},
+				Some(("endif", _)) => {
+					conditions.pop();
+				}
+				_ => {}
+			}
+
+			let nested = !output.conditions.is_empty();
+			let disabled = output.conditions.iter()
+				.any(|c| c.active == Some(false));
+
+			match directive(trimmed) {
+				Some(("version", _)) if !disabled => {
+					// The first one wins.
+					if output.version.is_none() {
+						output.version = Some((
+							trimmed.to_string(), origin));
+					}
+				}
+				Some(("extension", _)) if !nested => output
+					.extensions.push((trimmed.to_string(), origin)),
+				Some(("include", path)) if !disabled => {
+					let path = path.strip_prefix('"')
+						.and_then(|p| p.strip_suffix('"'))
+						.ok_or_else(|| error(
+							"Expected `#include \"file\"`"
+							.to_string()))?;
+					let resolved = self.include.as_mut()
+						.and_then(|i| i.resolve(path, &name));
+					let (included, source) = match resolved {
+						Some(resolved) => resolved,
+						// The branch may not be taken.
+						None if nested => {
+							output.body.push((
+								line.to_string(),
+								origin));
+							continue;
+						}
+						None => return Err(error(format!(
+							"Can't include `{}`", path))),
+					};
+					let index = match output.files.iter()
+						.position(|f| *f == included)
+					{
+						Some(index) => index,
+						None => {
+							output.files.push(included);
+							output.files.len() - 1
+						}
+					};
+
+					if stack.contains(&index) {
+						return Err(error(format!(
+							"`{}` includes itself", path)));
+					}
+					stack.push(index);
+					self.expand(index, &source, stack, output)?;
+					stack.pop();
+				}
+				_ if is_precision(trimmed) && !nested => {
+					// Included files may repeat them.
+					if !output.precisions.iter()
+						.any(|p| p.0 == trimmed)
+					{
+						output.precisions.push((
+							trimmed.to_string(), origin));
+					}
+				}
+				_ => output.body.push((line.to_string(), origin)),
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// A line of output, and the file & line it came from (`None` for defines).
+type Line = (String, Option<(usize, u32)>);
+
+#[derive(Default)]
+struct Output {
+	version: Option<Line>,
+	extensions: Vec<Line>,
+	precisions: Vec<Line>,
+	body: Vec<Line>,
+	files: Vec<String>,
+	// The `#if`s the current line is inside of.
+	conditions: Vec<Condition>,
+}
+
+/// A conditional group, with `Some` if the preprocessor knows whether the
+/// current branch is taken.
+struct Condition {
+	active: Option<bool>,
+	// If an earlier branch was taken.
+	taken: Option<bool>,
+}
+
+impl Condition {
+	fn new(active: Option<bool>) -> Self {
+		Condition { active, taken: active }
+	}
+
+	/// Go to an `#elif` branch.
+	fn elif(&mut self, condition: Option<bool>) {
+		self.active = match (self.taken, condition) {
+			(Some(true), _) | (_, Some(false)) => Some(false),
+			(Some(false), condition) => condition,
+			(None, _) => None,
+		};
+		self.taken = match (self.taken, self.active) {
+			(Some(true), _) | (_, Some(true)) => Some(true),
+			(Some(false), Some(false)) => Some(false),
+			_ => None,
+		};
+	}
+
+	/// Go to the `#else` branch.
+	fn otherwise(&mut self) {
+		self.active = self.taken.map(|taken| !taken);
+		self.taken = Some(true);
+	}
+}
+
+/// Split `# name rest` into the name and the rest.
+fn directive(line: &str) -> Option<(&str, &str)> {
+	let line = line.strip_prefix('#')?.trim_start();
+	let end = line.find(char::is_whitespace).unwrap_or(line.len());
+
+	Some((&line[..end], line[end..].trim()))
+}
+
+/// If `line` is a default precision statement, like `precision highp float;`.
+fn is_precision(line: &str) -> bool {
+	line.strip_prefix("precision")
+		.is_some_and(|rest| rest.starts_with(char::is_whitespace))
+}
+
+/// The value of an `#if 0` or `#if 1` condition, `None` for anything else.
+fn literal(condition: &str) -> Option<bool> {
+	match condition {
+		"0" => Some(false),
+		"1" => Some(true),
+		_ => None,
+	}
+}
+
+/// Preprocessed shader source, with where each line came from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderSource {
+	code: String,
+	files: Vec<String>,
+	// The file (index into `files`) and line of each line of `code`.
+	lines: Vec<Option<(usize, u32)>>,
+}
+
+impl ShaderSource {
+	/// Get the source to compile.
+	pub fn code(&self) -> &str {
+		&self.code
+	}
+
+	/// Get the names of the files it was made from, starting with the
+	/// top-level file.
+	pub fn files(&self) -> &[String] {
+		&self.files
+	}
+
+	/// Get the file and line that `line` (starting at 1) of the code came
+	/// from, `None` for defines.
+	pub fn origin(&self, line: u32) -> Option<(&str, u32)> {
+		let origin = (*self.lines.get((line as usize).checked_sub(1)?)?)?;
+
+		Some((&self.files[origin.0], origin.1))
+	}
+
+	/// Point a diagnostic from compiling the code at the original file and
+	/// line.
+	pub(crate) fn map(&self, diagnostic: &mut Diagnostic) {
+		if let Some((file, line)) = diagnostic.line
+			.and_then(|line| self.origin(line))
+		{
+			diagnostic.file = Some(file.to_string());
+			diagnostic.line = Some(line);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn lib(path: &str) -> Option<String> {
+		match path {
+			"lib.glsl" => Some("#extension GL_OES_standard_derivatives \
+				: enable\nfloat lib();\n".to_string()),
+			"loop.glsl" => Some("#include \"loop.glsl\"\n".to_string()),
+			_ => None,
+		}
+	}
+
+	fn code(source: &str) -> String {
+		Preprocessor::new().include(lib).process("main.glsl",
+			source.as_bytes()).unwrap().code().to_string()
+	}
+
+	#[test]
+	fn hoists_version_and_extensions() {
+		let source = Preprocessor::new().include(lib).define("N", "4")
+			.process("main.glsl", b"// main\n#include \"lib.glsl\"\n\
+				#version 100\nvoid main() {}\n").unwrap();
+
+		assert_eq!(source.code(), "#version 100\n#extension \
+			GL_OES_standard_derivatives : enable\n#define N 4\n\
+			// main\nfloat lib();\nvoid main() {}\n");
+		assert_eq!(source.files(), ["main.glsl", "lib.glsl"]);
+	}
+
+	#[test]
+	fn line_map() {
+		let source = Preprocessor::new().include(lib).define("N", "4")
+			.process("main.glsl", b"#version 100\n\
+				#include \"lib.glsl\"\nvoid main() {}\n").unwrap();
+
+		assert_eq!(source.origin(1), Some(("main.glsl", 1)));
+		assert_eq!(source.origin(2), Some(("lib.glsl", 1)));
+		assert_eq!(source.origin(3), None);
+		assert_eq!(source.origin(4), Some(("lib.glsl", 2)));
+		assert_eq!(source.origin(5), Some(("main.glsl", 3)));
+		assert_eq!(source.origin(0), None);
+		assert_eq!(source.origin(6), None);
+	}
+
+	#[test]
+	fn conditionals_stay_in_place() {
+		assert_eq!(code("#version 110\n#ifdef GL_ES\n\
+			precision mediump float;\n#extension GL_foo : enable\n\
+			#endif\n"), "#version 110\n#ifdef GL_ES\n\
+			precision mediump float;\n#extension GL_foo : enable\n\
+			#endif\n");
+	}
+
+	#[test]
+	fn hoists_precision() {
+		let include = |path: &str| match path {
+			"light.glsl" => Some("precision mediump float;\n\
+				uniform vec3 light;\n".to_string()),
+			_ => None,
+		};
+		let source = Preprocessor::new().include(include)
+			.define("N", "4").process("main.frag", b"#version 100\n\
+				#include \"light.glsl\"\n\
+				precision mediump float;\nvoid main() {}\n")
+			.unwrap();
+
+		assert_eq!(source.code(), "#version 100\n#define N 4\n\
+			precision mediump float;\nuniform vec3 light;\n\
+			void main() {}\n");
+		assert_eq!(source.origin(3), Some(("light.glsl", 1)));
+	}
+
+	#[test]
+	fn disabled_version() {
+		assert_eq!(code("#if 0\n#version 300 es\n#endif\n\
+			#version 100\n"), "#version 100\n#if 0\n\
+			#version 300 es\n#endif\n");
+	}
+
+	#[test]
+	fn disabled_includes() {
+		let source = "#if 0\n#include \"lib.glsl\"\n#elif 1\n\
+			#else\n#include \"missing.glsl\"\n#endif\n";
+
+		assert_eq!(code(source), source);
+		assert_eq!(code("#if 1\n#else\n#include \"lib.glsl\"\n#endif\n"),
+			"#if 1\n#else\n#include \"lib.glsl\"\n#endif\n");
+		// The driver decides if `GL_ES` is defined.
+		assert_eq!(code("#ifdef GL_ES\n#include \"missing.glsl\"\n\
+			#else\n#include \"lib.glsl\"\n#endif\n"), "#ifdef GL_ES\n\
+			#include \"missing.glsl\"\n#else\n#extension \
+			GL_OES_standard_derivatives : enable\nfloat lib();\n\
+			#endif\n");
+	}
+
+	#[test]
+	fn include_errors() {
+		let error = Preprocessor::new().include(lib).process("main.glsl",
+			b"\n#include \"missing.glsl\"\n").unwrap_err();
+
+		assert_eq!((error.file.as_str(), error.line),
+			("main.glsl", Some(2)));
+
+		let error = Preprocessor::new().include(lib).process("main.glsl",
+			b"#include \"loop.glsl\"\n").unwrap_err();
+
+		assert_eq!((error.file.as_str(), error.line),
+			("loop.glsl", Some(1)));
+
+		assert!(Preprocessor::new().process("main.glsl",
+			b"#include <lib.glsl>\n").is_err());
+	}
+}
@@ -5,31 +5,161 @@
 
 use UniformData;
 use VertexData;
-use OpenGL;
-use std::{ rc::Rc, ops::Range };
+use { Binding, OpenGL };
+use std::{ rc::Rc, ops::Range, ptr, fs, time::SystemTime };
+use std::cell::{ Cell, RefCell };
+use std::collections::HashMap;
+use std::path::{ Path, PathBuf };
 use types::*;
-use Topology;
-
-static mut CURRENT_PROGRAM: GLuint = 0; // 0 is always invalid program.
+use diagnostic;
+use reflect;
+use { Capability, Diagnostic, LinkError, PreprocessError, Preprocessor,
+	ProgramCache, Severity, ShaderError, ShaderSource, Stage, Topology,
+	Variable };
 
 /// A loaded GPU program.
 #[derive(Clone)] pub struct Program(pub(crate) Rc<ProgramContext>);
 
 impl Program {
-	/// Load a shader program
+	/// Load a shader program.  Panics if it fails to compile or link.
 	pub fn new(opengl: &OpenGL, vertex: &[u8], fragment: &[u8]) -> Self {
-		// Compile vertex & fragment shaders
-		let v_shader = shader_new(opengl, 0x8B31/*vertex*/, vertex);
-		let f_shader = shader_new(opengl, 0x8B30/*fragment*/, fragment);
-		// Link shaders together.
-		let program = gl!(opengl, (opengl.get().create_program)());
-		gl!(opengl, (opengl.get().attach_shader)(program, v_shader));
-		gl!(opengl, (opengl.get().attach_shader)(program, f_shader));
-		gl!(opengl, (opengl.get().link_program)(program));
-		gl!(opengl, (opengl.get().detach_shader)(program, v_shader));
-		gl!(opengl, (opengl.get().detach_shader)(program, f_shader));
-		// Return
-		Program(Rc::new(ProgramContext(program, opengl.clone())))
+		match Self::try_new(opengl, vertex, fragment) {
+			Ok(program) => program,
+			Err(error) => panic!("{}", error),
+		}
+	}
+
+	/// Load a shader program, or get why it failed to compile or link.
+	pub fn try_new(opengl: &OpenGL, vertex: &[u8], fragment: &[u8])
+		-> Result<Self, ShaderError>
+	{
+		let linked = link(opengl, vertex, fragment, &[], false)?;
+
+		Ok(Program::from_linked(opengl, linked, None))
+	}
+
+	/// Load a shader program, from a binary in `cache` if there's one for
+	/// this source and driver, and if not save its binary there.  Falls
+	/// back to compiling if program binaries aren't supported, or the
+	/// driver rejects the cached binary.
+	pub fn try_cached(opengl: &OpenGL, cache: &ProgramCache, vertex: &[u8],
+		fragment: &[u8]) -> Result<Self, ShaderError>
+	{
+		if !opengl.has(Capability::ProgramBinary) {
+			return Self::try_new(opengl, vertex, fragment);
+		}
+
+		let key = cache.key(opengl, vertex, fragment);
+		let cached = cache.read(key).and_then(|(format, binary)| {
+			load_binary(opengl, format, &binary)
+		});
+		let linked = match cached {
+			Some(linked) => linked,
+			None => {
+				// `GL_OES_get_program_binary` has no hint.
+				let linked = link(opengl, vertex, fragment, &[],
+					opengl.core_binary())?;
+
+				if let Some((format, binary)) =
+					get_binary(opengl, linked.program)
+				{
+					cache.write(key, format, &binary);
+				}
+				linked
+			}
+		};
+
+		Ok(Program::from_linked(opengl, linked, None))
+	}
+
+	/// Load a shader program from preprocessed source, with diagnostics
+	/// pointing at the original files and lines.
+	pub fn try_from_sources(opengl: &OpenGL, vertex: &ShaderSource,
+		fragment: &ShaderSource) -> Result<Self, ShaderError>
+	{
+		let linked = link_sources(opengl, vertex, fragment, &[])?;
+
+		Ok(Program::from_linked(opengl, linked, None))
+	}
+
+	/// Load a shader program from the files `vertex` and `fragment`, with
+	/// `preprocessor`.  Use `reload_if_changed()` to load them again when
+	/// they (or the files they include) change.
+	pub fn from_files<P: AsRef<Path>>(opengl: &OpenGL,
+		mut preprocessor: Preprocessor<'static>, vertex: P, fragment: P)
+		-> Result<Self, ShaderError>
+	{
+		let paths = [vertex.as_ref().to_path_buf(),
+			fragment.as_ref().to_path_buf()];
+		let sources = read_sources(&mut preprocessor, &paths)?;
+		let linked = link_sources(opengl, &sources[0], &sources[1],
+			&[])?;
+		let files = Files {
+			preprocessor,
+			modified: modified(&sources),
+			paths,
+		};
+
+		Ok(Program::from_linked(opengl, linked, Some(files)))
+	}
+
+	/// If any file of a program made with `from_files()` changed since it
+	/// was loaded, load it again.  Returns true if it was reloaded, and
+	/// the error if it failed to, in which case the old program is kept.
+	///
+	/// Handles from `uniform()` and `vertex_data()` stay valid, but the
+	/// uniforms have their default values again.
+	pub fn reload_if_changed(&self) -> Result<bool, ShaderError> {
+		let mut files = match self.0.files {
+			Some(ref files) => files.borrow_mut(),
+			None => return Ok(false),
+		};
+		let changed = files.modified.iter()
+			.any(|(path, time)| mtime(path) != *time);
+
+		if !changed {
+			return Ok(false);
+		}
+
+		let files = &mut *files;
+		let sources = read_sources(&mut files.preprocessor,
+			&files.paths);
+		// Don't try again until a file changes again.
+		files.modified = match sources {
+			Ok(ref sources) => modified(sources),
+			Err(_) => files.modified.iter()
+				.map(|(path, _)| (path.clone(), mtime(path)))
+				.collect(),
+		};
+		let sources = sources?;
+		// Keep the attribute locations, so vertex data stays set.
+		let attributes = self.attributes();
+		let linked = link_sources(&self.0.opengl, &sources[0],
+			&sources[1], &attributes)?;
+
+		self.0.program.set(linked.program);
+		// Drops the old program.
+		*self.0.linked.borrow_mut() = linked;
+		self.0.cache.borrow_mut().clear();
+		self.0.generation.set(self.0.generation.get() + 1);
+
+		Ok(true)
+	}
+
+	/// Get the warnings (and other messages) from compiling and linking
+	/// this program.  A reload doesn't change the ones already returned.
+	pub fn warnings(&self) -> Rc<[Diagnostic]> {
+		self.0.linked.borrow().warnings.clone()
+	}
+
+	/// Get the active vertex attributes.
+	pub fn attributes(&self) -> Rc<[Variable]> {
+		self.0.linked.borrow().attributes.clone()
+	}
+
+	/// Get the active uniforms.  Arrays are one entry, with their size.
+	pub fn uniforms(&self) -> Rc<[Variable]> {
+		self.0.linked.borrow().uniforms.clone()
 	}
 
 	/// Get a vertex data handle for this GPU program.
@@ -42,10 +172,29 @@ impl Program {
 		UniformData::new(self, name)
 	}
 
+	/// Bind the uniform block `name` to the `UniformBuffer` binding point
+	/// `binding`.  Returns false if there's no such block, or uniform
+	/// buffers aren't supported.
+	pub fn bind_uniform_block(&self, name: &[u8], binding: u32) -> bool {
+		// Last character in slice needs to null for it to be safe.
+		assert_eq!(name[name.len() -1], b'\0');
+		let index = gl!(self.0.opengl, ?get_uniform_block_index(
+			self.get(), name.as_ptr() as *const _));
+
+		match index {
+			Some(index) if index != GL_INVALID_INDEX => {
+				gl!(self.0.opengl, ?uniform_block_binding(
+					self.get(), index, binding));
+				true
+			}
+			_ => false,
+		}
+	}
+
 	/// Draw the elements.
 	pub fn draw_arrays(&self, topology: Topology, range: Range<u32>) {
 		self.bind();
-		gl!((*self.0).1, ((*self.0).1.get().draw_arrays)(
+		gl!(self.0.opengl, draw_arrays(
 			topology as GLuint,
 			range.start as GLint, range.end as GLsizei));
 	}
@@ -54,65 +203,462 @@ impl Program {
 	pub(crate) fn bind(&self) {
 		let program = unsafe { self.get() };
 
-		if program != unsafe { CURRENT_PROGRAM } {
-			gl!(&(*self.0).1,
-				((*self.0).1.get().use_program)(program));
-			unsafe { CURRENT_PROGRAM = program; }
+		if program != self.0.opengl.get().bound_program.get() {
+			gl!(self.0.opengl, use_program(program));
+			self.0.opengl.get().bound_program.set(program);
 		}
 	}
 
 	/// Get a new OpenGL reference
 	pub(crate) fn opengl(&self) -> OpenGL {
-		(*self.0).1.clone()
+		self.0.opengl.clone()
 	}
 
 	pub(crate) unsafe fn get(&self) -> GLuint {
-		(*self.0).0
+		self.0.program.get()
+	}
+
+	/// Get how many times the program was reloaded, to know when to look
+	/// up locations again.
+	pub(crate) fn generation(&self) -> u32 {
+		self.0.generation.get()
+	}
+
+	fn from_linked(opengl: &OpenGL, linked: Linked, files: Option<Files>)
+		-> Self
+	{
+		Program(Rc::new(ProgramContext {
+			program: Cell::new(linked.program),
+			linked: RefCell::new(linked),
+			opengl: opengl.clone(),
+			cache: RefCell::new(HashMap::new()),
+			generation: Cell::new(0),
+			files: files.map(RefCell::new),
+		}))
+	}
+
+	/// Store the value of elements `element..` of the uniform at `index`
+	/// in `uniforms()`, with `bytes` split evenly between `count`
+	/// elements.  Returns false if they already had that value, so it
+	/// doesn't need to be uploaded.
+	pub(crate) fn cache_uniform(&self, index: usize, element: i32,
+		count: usize, transpose: bool, bytes: &[u8]) -> bool
+	{
+		let mut cache = self.0.cache.borrow_mut();
+		let size = bytes.len() / count;
+		let elements = (element..).zip(bytes.chunks(size));
+		let changed = elements.clone().any(|(element, bytes)| {
+			match cache.get(&(index, element)) {
+				Some(&(t, ref b)) => t != transpose || b[..] != *bytes,
+				None => true,
+			}
+		});
+
+		if changed {
+			for (element, bytes) in elements {
+				cache.insert((index, element),
+					(transpose, bytes.to_vec()));
+			}
+		}
+
+		let opengl = self.0.opengl.get();
+		let mut stats = opengl.uniform_stats.get();
+		if changed {
+			stats.misses += 1;
+		} else {
+			stats.hits += 1;
+		}
+		opengl.uniform_stats.set(stats);
+
+		changed
 	}
 }
 
-pub(crate) struct ProgramContext(GLuint, OpenGL);
+pub(crate) struct ProgramContext {
+	// The same as `linked.program`, without borrowing.
+	program: Cell<GLuint>,
+	linked: RefCell<Linked>,
+	opengl: OpenGL,
+	cache: RefCell<UniformCache>,
+	// How many times the program was reloaded.
+	generation: Cell<u32>,
+	// The files of programs made with `from_files()`.
+	files: Option<RefCell<Files>>,
+}
+
+/// The last value uploaded to each (uniform, array element), and if it was
+/// transposed.
+type UniformCache = HashMap<(usize, i32), (bool, Vec<u8>)>;
+
+/// A linked OpenGL program, deleted on drop.
+struct Linked {
+	program: GLuint,
+	opengl: OpenGL,
+	warnings: Rc<[Diagnostic]>,
+	attributes: Rc<[Variable]>,
+	uniforms: Rc<[Variable]>,
+}
 
-impl Drop for ProgramContext {
+impl Drop for Linked {
 	fn drop(&mut self) {
-		gl!(self.1, (self.1.get().delete_program)(self.0));
+		if self.opengl.is_destroyed() {
+			return;
+		}
+		self.opengl.forget_binding(Binding::Program, self.program);
+		gl!(self.opengl, delete_program(self.program));
 	}
 }
 
-/// Compile a new shader.
-fn shader_new(opengl: &OpenGL, shader_type: GLenum, src: &[u8]) -> GLuint {
-	let shader = gl!(opengl, (opengl.get().create_shader)(shader_type));
-	gl!(opengl, (opengl.get().shader_source)(shader, 1 /*1 string*/,
+/// The files of a program, to reload it when they change.
+struct Files {
+	preprocessor: Preprocessor<'static>,
+	// The vertex & fragment shader.
+	paths: [PathBuf; 2],
+	// Every file read, and when it was modified.
+	modified: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+/// Read and preprocess the vertex & fragment shader files.
+fn read_sources(preprocessor: &mut Preprocessor, paths: &[PathBuf; 2])
+	-> Result<[ShaderSource; 2], ShaderError>
+{
+	let mut read = |path: &PathBuf| {
+		let name = path.to_string_lossy();
+		let source = fs::read(path).map_err(|error| PreprocessError {
+			file: name.to_string(),
+			line: None,
+			message: error.to_string(),
+		})?;
+
+		preprocessor.process(&name, &source)
+	};
+
+	Ok([read(&paths[0])?, read(&paths[1])?])
+}
+
+/// Get when each file of `sources` was modified.
+fn modified(sources: &[ShaderSource; 2])
+	-> Vec<(PathBuf, Option<SystemTime>)>
+{
+	sources.iter().flat_map(|source| source.files()).map(|file| {
+		let path = PathBuf::from(file);
+		let time = mtime(&path);
+
+		(path, time)
+	}).collect()
+}
+
+/// Get when a file was modified, `None` if it can't be read.
+fn mtime(path: &Path) -> Option<SystemTime> {
+	fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Compile and link preprocessed source, pointing diagnostics at the
+/// original files and lines.
+fn link_sources(opengl: &OpenGL, vertex: &ShaderSource,
+	fragment: &ShaderSource, attributes: &[Variable])
+	-> Result<Linked, ShaderError>
+{
+	let map = |diagnostic: &mut Diagnostic| match diagnostic.stage {
+		Stage::Vertex => vertex.map(diagnostic),
+		Stage::Fragment => fragment.map(diagnostic),
+		Stage::Link => {}
+	};
+
+	match link(opengl, vertex.code().as_bytes(),
+		fragment.code().as_bytes(), attributes, false)
+	{
+		Ok(mut linked) => {
+			let mut warnings = linked.warnings.to_vec();
+
+			warnings.iter_mut().for_each(map);
+			linked.warnings = warnings.into();
+			Ok(linked)
+		}
+		Err(ShaderError::Compile(mut diagnostics)) => {
+			diagnostics.iter_mut().for_each(map);
+			Err(ShaderError::Compile(diagnostics))
+		}
+		Err(error) => Err(error),
+	}
+}
+
+/// Compile and link a program, with `attributes` bound to their locations.
+/// If `retrievable`, hint that its binary will be read.
+fn link(opengl: &OpenGL, vertex: &[u8], fragment: &[u8],
+	attributes: &[Variable], retrievable: bool)
+	-> Result<Linked, ShaderError>
+{
+	// Compile vertex & fragment shaders
+	let mut diagnostics = Vec::new();
+	let (v_shader, v_ok) = shader_new(opengl, Stage::Vertex, vertex,
+		&mut diagnostics);
+	let (f_shader, f_ok) = shader_new(opengl, Stage::Fragment, fragment,
+		&mut diagnostics);
+	if !(v_ok && f_ok) {
+		gl!(opengl, delete_shader(v_shader));
+		gl!(opengl, delete_shader(f_shader));
+		return Err(ShaderError::Compile(diagnostics));
+	}
+	// Link shaders together.
+	let program = gl!(opengl, create_program());
+	// Deletes the program if linking fails.
+	let mut linked = Linked {
+		program,
+		opengl: opengl.clone(),
+		warnings: Rc::new([]),
+		attributes: Rc::new([]),
+		uniforms: Rc::new([]),
+	};
+	gl!(opengl, attach_shader(program, v_shader));
+	gl!(opengl, attach_shader(program, f_shader));
+	for attribute in attributes {
+		let name = attribute.name.trim_end_matches("[0]");
+		let name = format!("{}\0", name);
+
+		gl!(opengl, bind_attrib(program, attribute.location as GLuint,
+			name.as_ptr() as *const _));
+	}
+	if retrievable {
+		gl!(opengl, ?program_parameter(program,
+			GL_PROGRAM_BINARY_RETRIEVABLE_HINT, 1));
+	}
+	gl!(opengl, link_program(program));
+	gl!(opengl, detach_shader(program, v_shader));
+	gl!(opengl, detach_shader(program, f_shader));
+	// The program keeps what it needs, so the shaders can go.
+	gl!(opengl, delete_shader(v_shader));
+	gl!(opengl, delete_shader(f_shader));
+	let log = link_errors(opengl, program)?;
+	// Keep the warnings of successful compiles & links.
+	diagnostics.extend(diagnostic::parse(Stage::Link, &log, b"",
+		Severity::Info));
+	linked.warnings = diagnostics.into();
+	linked.attributes = reflect::active(opengl, program, false).into();
+	linked.uniforms = reflect::active(opengl, program, true).into();
+
+	Ok(linked)
+}
+
+/// Load a program from a binary, `None` if the driver rejects it.
+fn load_binary(opengl: &OpenGL, format: GLenum, binary: &[u8])
+	-> Option<Linked>
+{
+	let program = gl!(opengl, create_program());
+	// Deletes the program if loading fails.
+	let mut linked = Linked {
+		program,
+		opengl: opengl.clone(),
+		warnings: Rc::new([]),
+		attributes: Rc::new([]),
+		uniforms: Rc::new([]),
+	};
+	let load = {
+		let c = opengl.get();
+
+		if opengl.core_binary() {
+			c.optional(&c.functions.program_binary)?
+		} else {
+			c.optional(&c.functions.program_binary_oes)?
+		}
+	};
+
+	// An old binary is an error, which isn't worth reporting.
+	unsafe {
+		load(program, format, binary.as_ptr() as *const _,
+			binary.len() as GLsizei);
+	}
+	opengl.clear_errors();
+	link_errors(opengl, program).ok()?;
+	linked.attributes = reflect::active(opengl, program, false).into();
+	linked.uniforms = reflect::active(opengl, program, true).into();
+
+	Some(linked)
+}
+
+/// Get the binary format and binary of a linked program.
+fn get_binary(opengl: &OpenGL, program: GLuint) -> Option<(GLenum, Vec<u8>)>
+{
+	let mut length = 0;
+	let mut written = 0;
+	let mut format = 0;
+
+	gl!(opengl, get_program(program, GL_PROGRAM_BINARY_LENGTH,
+		&mut length));
+	if length <= 0 {
+		return None;
+	}
+
+	let mut binary = vec![0u8; length as usize];
+	let data = binary.as_mut_ptr() as *mut _;
+	if opengl.core_binary() {
+		gl!(opengl, ?get_program_binary(program, length, &mut written,
+			&mut format, data))?;
+	} else {
+		gl!(opengl, ?get_program_binary_oes(program, length,
+			&mut written, &mut format, data))?;
+	}
+	binary.truncate(written.max(0) as usize);
+
+	Some((format, binary))
+}
+
+/// Compile a new shader, adding any diagnostics to `diagnostics` (errors, or
+/// warnings if it compiled).  Returns the shader, and if it compiled.
+fn shader_new(opengl: &OpenGL, stage: Stage, src: &[u8],
+	diagnostics: &mut Vec<Diagnostic>) -> (GLuint, bool)
+{
+	let shader = gl!(opengl, create_shader(stage.gl_enum()));
+	gl!(opengl, shader_source(shader, 1 /*1 string*/,
 		[src.as_ptr() as *const _].as_ptr(), [src.len() as i32].as_ptr()
 	));
-	gl!(opengl, (opengl.get().compile_shader)(shader));
-	compile_errors(opengl, shader);
-	shader
-}
-
-/// Evaluate and panic with error message if failed to compile, does nothing in
-/// release mode
-fn compile_errors(_opengl: &OpenGL, _shader: GLuint) {
-	#[cfg(debug_assertions)] {
-		let mut value = unsafe { ::std::mem::uninitialized() };
-
-		gl!(_opengl, (_opengl.get().get_shader)(_shader,
-			0x8B81 /*GL_COMPILE_STATUS*/, &mut value));
-
-		if value == 0 {
-			let mut value = unsafe { ::std::mem::uninitialized() };
-			gl!(_opengl, (_opengl.get().get_shader)(_shader,
-				0x8B84 /*GL_INFO_LOG_LENGTH*/,
-				&mut value));
-			let mut buffer: Vec<u8> =
-				vec![unsafe { ::std::mem::uninitialized() };
-					value as usize];
-			gl!(_opengl, (_opengl.get().info_log)(_shader,
-				value as GLsizei, ::std::ptr::null_mut(),
-				buffer.as_mut_ptr() as *mut _));
-			panic!("Failed to compile: {}.",
-				::std::str::from_utf8(buffer.as_slice())
-					.unwrap());
-		}
+	gl!(opengl, compile_shader(shader));
+
+	let mut compiled = 0;
+	let mut value = 0;
+	gl!(opengl, get_shader(shader, GL_COMPILE_STATUS, &mut compiled));
+	gl!(opengl, get_shader(shader, GL_INFO_LOG_LENGTH, &mut value));
+	let log = read_log(value, |length, buffer| {
+		gl!(opengl, info_log(shader, length, ptr::null_mut(), buffer));
+	});
+
+	if compiled != 0 {
+		diagnostics.extend(diagnostic::parse(stage, &log, src,
+			Severity::Info));
+		return (shader, true);
+	}
+
+	let mut parsed = diagnostic::parse(stage, &log, src, Severity::Error);
+
+	if parsed.is_empty() {
+		parsed.push(Diagnostic {
+			stage, file: None, line: None, column: None,
+			severity: Severity::Error,
+			message: "Failed to compile".to_string(),
+			source: None,
+		});
+	}
+	diagnostics.extend(parsed);
+	(shader, false)
+}
+
+/// Get the link log, or the error if the program failed to link.
+fn link_errors(opengl: &OpenGL, program: GLuint) -> Result<String, LinkError> {
+	let mut linked = 0;
+	let mut value = 0;
+
+	gl!(opengl, get_program(program, GL_LINK_STATUS, &mut linked));
+	gl!(opengl, get_program(program, GL_INFO_LOG_LENGTH, &mut value));
+
+	let log = read_log(value, |length, buffer| {
+		gl!(opengl, program_info_log(program, length, ptr::null_mut(),
+			buffer));
+	});
+
+	if linked == 0 {
+		return Err(LinkError::new(log));
+	}
+	Ok(log)
+}
+
+/// Read an info log that's `length` bytes long (including the nul) with
+/// `read`.
+fn read_log<F>(length: GLint, read: F) -> String
+	where F: FnOnce(GLsizei, *mut GLchar)
+{
+	let mut buffer: Vec<u8> = vec![0; length.max(0) as usize];
+
+	if length > 0 {
+		read(length, buffer.as_mut_ptr() as *mut _);
+	}
+	// Drop the nul terminator.
+	while buffer.last() == Some(&0) {
+		buffer.pop();
+	}
+
+	String::from_utf8_lossy(&buffer).into_owned()
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+	use std::{ env, fs, time::{ Duration, SystemTime } };
+	use mock::MockBackend;
+	use { Capability, GlslType, Preprocessor, Program, ProgramCache };
+
+	#[test]
+	fn reload_keeps_returned_values() {
+		let dir = env::temp_dir().join(format!("asi_opengl_reload_{}",
+			std::process::id()));
+		let vertex = dir.join("reload.vert");
+		let fragment = dir.join("reload.frag");
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(&vertex, "void main() {}\n").unwrap();
+		fs::write(&fragment, "void main() {}\n").unwrap();
+
+		let mock = MockBackend::new();
+		mock.set_uniforms(&[("a", GlslType::Float, 1)]);
+		let opengl = mock.opengl();
+		let program = Program::from_files(&opengl, Preprocessor::new(),
+			&vertex, &fragment).unwrap();
+		let uniforms = program.uniforms();
+
+		assert!(!program.reload_if_changed().unwrap());
+
+		mock.set_uniforms(&[("b", GlslType::Vec2, 1)]);
+		fs::File::options().write(true).open(&fragment).unwrap()
+			.set_modified(SystemTime::now() + Duration::from_secs(60))
+			.unwrap();
+
+		assert!(program.reload_if_changed().unwrap());
+		assert_eq!(uniforms[0].name, "a");
+		assert_eq!(program.uniforms()[0].name, "b");
+		assert_eq!(program.uniforms()[0].kind, GlslType::Vec2);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	/// Load a program twice with a new cache, and get the functions called.
+	fn cached_twice(mock: &MockBackend, name: &str) -> Vec<&'static str> {
+		let dir = env::temp_dir().join(format!("asi_opengl_{}_{}", name,
+			std::process::id()));
+		let cache = ProgramCache::new(&dir);
+		let opengl = mock.opengl();
+
+		assert!(opengl.has(Capability::ProgramBinary));
+		Program::try_cached(&opengl, &cache, b"v", b"f").unwrap();
+		Program::try_cached(&opengl, &cache, b"v", b"f").unwrap();
+		fs::remove_dir_all(&dir).unwrap();
+
+		mock.names()
+	}
+
+	#[test]
+	fn cached_core_binary() {
+		let mock = MockBackend::new();
+		mock.set_version("OpenGL ES 3.0 Mock");
+		let names = cached_twice(&mock, "core_binary");
+
+		assert_eq!(names.iter().filter(|n| **n == "glLinkProgram")
+			.count(), 1);
+		assert!(names.contains(&"glProgramParameteri"));
+		assert!(names.contains(&"glGetProgramBinary"));
+		assert!(names.contains(&"glProgramBinary"));
+	}
+
+	#[test]
+	fn cached_oes_binary() {
+		let mock = MockBackend::new();
+		assert!(!mock.opengl().has(Capability::ProgramBinary));
+
+		mock.set_extensions("GL_OES_standard_derivatives \
+			GL_OES_get_program_binary");
+		let names = cached_twice(&mock, "oes_binary");
+
+		assert_eq!(names.iter().filter(|n| **n == "glLinkProgram")
+			.count(), 1);
+		assert!(!names.contains(&"glProgramParameteri"));
+		assert!(!names.contains(&"glGetProgramBinary"));
+		assert!(names.contains(&"glGetProgramBinaryOES"));
+		assert!(names.contains(&"glProgramBinaryOES"));
 	}
 }
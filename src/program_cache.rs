@@ -0,0 +1,97 @@
+// Copyright Jeron A. Lau 2018.
+// Dual-licensed under either the MIT License or the Boost Software License,
+// Version 1.0.  (See accompanying file LICENSE_1_0.txt or copy at
+// https://www.boost.org/LICENSE_1_0.txt)
+
+use std::{ fs, path::PathBuf };
+use OpenGL;
+use types::*;
+
+/// Starts every cached binary file.
+const MAGIC: &[u8; 4] = b"ASIB";
+
+/// A directory of linked program binaries, used by `Program::try_cached()`
+/// to skip compiling.  Each binary is only used with the same source and the
+/// same `GL_RENDERER` and `GL_VERSION`.
+#[derive(Clone, Debug)]
+pub struct ProgramCache {
+	dir: PathBuf,
+}
+
+impl ProgramCache {
+	/// Use the directory `dir`, which is created when a binary is saved.
+	pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+		ProgramCache { dir: dir.into() }
+	}
+
+	/// Get the key for a program's source on the current driver.
+	pub(crate) fn key(&self, opengl: &OpenGL, vertex: &[u8], fragment: &[u8])
+		-> u64
+	{
+		let renderer = opengl.string(GL_RENDERER);
+		let version = opengl.string(GL_VERSION);
+		let mut hash = Fnv::new();
+
+		for part in &[vertex, fragment, renderer.as_bytes(),
+			version.as_bytes()]
+		{
+			// The length keeps the parts apart.
+			hash.write(&(part.len() as u64).to_le_bytes());
+			hash.write(part);
+		}
+		hash.0
+	}
+
+	/// Get the binary format and binary for `key`, if it's cached.
+	pub(crate) fn read(&self, key: u64) -> Option<(GLenum, Vec<u8>)> {
+		let file = fs::read(self.path(key)).ok()?;
+		let rest = file.strip_prefix(&MAGIC[..])?;
+
+		if rest.len() < 4 {
+			return None;
+		}
+
+		let format = u32::from_le_bytes([rest[0], rest[1], rest[2],
+			rest[3]]);
+
+		Some((format, rest[4..].to_vec()))
+	}
+
+	/// Save the binary for `key`.  Failing to is ignored, as the program
+	/// can be compiled again.
+	pub(crate) fn write(&self, key: u64, format: GLenum, binary: &[u8]) {
+		let mut file = MAGIC.to_vec();
+
+		file.extend_from_slice(&format.to_le_bytes());
+		file.extend_from_slice(binary);
+
+		// Write to another file first, so a crash doesn't leave a
+		// partial binary.
+		let path = self.path(key);
+		let temporary = path.with_extension("tmp");
+		let _ = fs::create_dir_all(&self.dir)
+			.and_then(|_| fs::write(&temporary, &file))
+			.and_then(|_| fs::rename(&temporary, &path));
+	}
+
+	fn path(&self, key: u64) -> PathBuf {
+		self.dir.join(format!("{:016x}.bin", key))
+	}
+}
+
+/// The 64-bit FNV-1a hash, which (unlike `DefaultHasher`) stays the same
+/// between Rust versions.
+struct Fnv(u64);
+
+impl Fnv {
+	fn new() -> Fnv {
+		Fnv(0xcbf2_9ce4_8422_2325)
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 ^= u64::from(*byte);
+			self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+		}
+	}
+}
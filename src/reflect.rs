@@ -0,0 +1,208 @@
+// Copyright Jeron A. Lau 2018.
+// Dual-licensed under either the MIT License or the Boost Software License,
+// Version 1.0.  (See accompanying file LICENSE_1_0.txt or copy at
+// https://www.boost.org/LICENSE_1_0.txt)
+
+use std::fmt;
+use OpenGL;
+use types::*;
+
+/// The GLSL type of an attribute or uniform.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GlslType {
+	Float,
+	Vec2,
+	Vec3,
+	Vec4,
+	Int,
+	IVec2,
+	IVec3,
+	IVec4,
+	/// OpenGL ES 3.0
+	UInt,
+	/// OpenGL ES 3.0
+	UVec2,
+	/// OpenGL ES 3.0
+	UVec3,
+	/// OpenGL ES 3.0
+	UVec4,
+	Bool,
+	BVec2,
+	BVec3,
+	BVec4,
+	Mat2,
+	Mat3,
+	Mat4,
+	Sampler2D,
+	SamplerCube,
+	/// Any other type, by its OpenGL enum.
+	Other(u32),
+}
+
+impl GlslType {
+	pub(crate) fn from_gl_enum(kind: GLenum) -> GlslType {
+		use self::GlslType::*;
+
+		match kind {
+			0x1406 => Float,
+			0x8B50 => Vec2,
+			0x8B51 => Vec3,
+			0x8B52 => Vec4,
+			0x1404 => Int,
+			0x8B53 => IVec2,
+			0x8B54 => IVec3,
+			0x8B55 => IVec4,
+			0x1405 => UInt,
+			0x8DC6 => UVec2,
+			0x8DC7 => UVec3,
+			0x8DC8 => UVec4,
+			0x8B56 => Bool,
+			0x8B57 => BVec2,
+			0x8B58 => BVec3,
+			0x8B59 => BVec4,
+			0x8B5A => Mat2,
+			0x8B5B => Mat3,
+			0x8B5C => Mat4,
+			0x8B5E => Sampler2D,
+			0x8B60 => SamplerCube,
+			kind => Other(kind),
+		}
+	}
+
+	/// If this is any sampler type, including those that are `Other`
+	/// (like `sampler2DShadow`, `sampler3D` and `samplerExternalOES`).
+	pub fn is_sampler(self) -> bool {
+		matches!(self.gl_enum(),
+			0x8B5D..=0x8B64 // sampler1D - sampler2DRectShadow
+			| 0x8BE7 // __samplerExternal2DY2YEXT
+			| 0x8D66 // samplerExternalOES
+			| 0x8DC0..=0x8DC5 // sampler1DArray - samplerCubeShadow
+			| 0x8DC9..=0x8DD8 // isampler1D - usamplerBuffer
+			| 0x900C..=0x900F // samplerCubeArray - usamplerCubeArray
+			| 0x9108..=0x910D) // sampler2DMS - usampler2DMSArray
+	}
+
+	/// The OpenGL enum for this type, like `GL_FLOAT_VEC3`.
+	pub fn gl_enum(self) -> u32 {
+		use self::GlslType::*;
+
+		match self {
+			Float => 0x1406,
+			Vec2 => 0x8B50,
+			Vec3 => 0x8B51,
+			Vec4 => 0x8B52,
+			Int => 0x1404,
+			IVec2 => 0x8B53,
+			IVec3 => 0x8B54,
+			IVec4 => 0x8B55,
+			UInt => 0x1405,
+			UVec2 => 0x8DC6,
+			UVec3 => 0x8DC7,
+			UVec4 => 0x8DC8,
+			Bool => 0x8B56,
+			BVec2 => 0x8B57,
+			BVec3 => 0x8B58,
+			BVec4 => 0x8B59,
+			Mat2 => 0x8B5A,
+			Mat3 => 0x8B5B,
+			Mat4 => 0x8B5C,
+			Sampler2D => 0x8B5E,
+			SamplerCube => 0x8B60,
+			Other(kind) => kind,
+		}
+	}
+}
+
+impl fmt::Display for GlslType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use self::GlslType::*;
+
+		f.write_str(match *self {
+			Float => "float",
+			Vec2 => "vec2",
+			Vec3 => "vec3",
+			Vec4 => "vec4",
+			Int => "int",
+			IVec2 => "ivec2",
+			IVec3 => "ivec3",
+			IVec4 => "ivec4",
+			UInt => "uint",
+			UVec2 => "uvec2",
+			UVec3 => "uvec3",
+			UVec4 => "uvec4",
+			Bool => "bool",
+			BVec2 => "bvec2",
+			BVec3 => "bvec3",
+			BVec4 => "bvec4",
+			Mat2 => "mat2",
+			Mat3 => "mat3",
+			Mat4 => "mat4",
+			Sampler2D => "sampler2D",
+			SamplerCube => "samplerCube",
+			Other(kind) => return write!(f, "type {:#X}", kind),
+		})
+	}
+}
+
+/// An active attribute or uniform of a program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variable {
+	/// The name, arrays end with "[0]".
+	pub name: String,
+	pub kind: GlslType,
+	/// The number of elements, 1 if it's not an array.
+	pub size: i32,
+	pub location: i32,
+}
+
+/// Get the active attributes (if `uniforms` is false) or uniforms of a
+/// linked program.
+pub(crate) fn active(opengl: &OpenGL, program: GLuint, uniforms: bool)
+	-> Vec<Variable>
+{
+	let (count, max_length) = if uniforms {
+		(GL_ACTIVE_UNIFORMS, GL_ACTIVE_UNIFORM_MAX_LENGTH)
+	} else {
+		(GL_ACTIVE_ATTRIBUTES, GL_ACTIVE_ATTRIBUTE_MAX_LENGTH)
+	};
+	let mut count_value = 0;
+	let mut length_value = 0;
+
+	gl!(opengl, get_program(program, count, &mut count_value));
+	gl!(opengl, get_program(program, max_length, &mut length_value));
+
+	(0..count_value.max(0) as GLuint).map(|index| {
+		// Room for the nul terminator, even if the driver is wrong.
+		let mut name = vec![0u8; length_value.max(0) as usize + 1];
+		let mut length = 0;
+		let mut size = 0;
+		let mut kind = 0;
+
+		if uniforms {
+			gl!(opengl, active_uniform(program, index,
+				name.len() as GLsizei, &mut length, &mut size,
+				&mut kind, name.as_mut_ptr() as *mut _));
+		} else {
+			gl!(opengl, active_attrib(program, index,
+				name.len() as GLsizei, &mut length, &mut size,
+				&mut kind, name.as_mut_ptr() as *mut _));
+		}
+		name.truncate(length.max(0) as usize);
+		name.push(0);
+
+		let location = if uniforms {
+			gl!(opengl, uniform(program, name.as_ptr() as *const _))
+		} else {
+			gl!(opengl, vdata(program, name.as_ptr() as *const _))
+		};
+
+		name.pop();
+
+		Variable {
+			name: String::from_utf8_lossy(&name).into_owned(),
+			kind: GlslType::from_gl_enum(kind),
+			size,
+			location,
+		}
+	}).collect()
+}
@@ -0,0 +1,260 @@
+// Copyright Jeron A. Lau 2018.
+// Dual-licensed under either the MIT License or the Boost Software License,
+// Version 1.0.  (See accompanying file LICENSE_1_0.txt or copy at
+// https://www.boost.org/LICENSE_1_0.txt)
+
+use std::{ mem, slice };
+
+/// A value that can be written to a `UniformBuffer` with the std140 layout.
+/// Implement it for structs with `std140!`.
+pub trait Std140 {
+	/// The base alignment in bytes, 16 for arrays and structs.
+	const ALIGN: usize;
+
+	/// Write the value, after `Std140Writer::write()` aligned it.
+	fn write_std140(&self, writer: &mut Std140Writer);
+}
+
+/// Implement `Std140` for a struct, writing the listed fields in order, like
+/// `std140!(Camera { view, eye, time });`.  Arrays should be fields of type
+/// `Vec<T>`.
+#[macro_export]
+macro_rules! std140 {
+	($t: ty { $($field: ident),* $(,)* }) => (
+		impl $crate::Std140 for $t {
+			const ALIGN: usize = 16;
+
+			fn write_std140(&self, writer: &mut $crate::Std140Writer) {
+				$(writer.write(&self.$field);)*
+				// The next member starts after the padding.
+				writer.align(16);
+			}
+		}
+	)
+}
+
+/// Writes values with the std140 layout.
+#[derive(Clone, Debug, Default)]
+pub struct Std140Writer {
+	bytes: Vec<u8>,
+}
+
+impl Std140Writer {
+	/// Start with no bytes.
+	pub fn new() -> Self {
+		Std140Writer { bytes: Vec::new() }
+	}
+
+	/// Align to the value's base alignment, then write it.
+	pub fn write<T: Std140 + ?Sized>(&mut self, value: &T) -> &mut Self {
+		self.align(T::ALIGN);
+		value.write_std140(self);
+		self
+	}
+
+	/// Pad with zeros to a multiple of `align` bytes.
+	pub fn align(&mut self, align: usize) -> &mut Self {
+		let length = self.bytes.len().next_multiple_of(align);
+
+		self.bytes.resize(length, 0);
+		self
+	}
+
+	/// Get the bytes written so far.
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes
+	}
+
+	/// Write the bytes of plain values (floats and integers).
+	fn push<T: Copy>(&mut self, values: &[T]) {
+		self.bytes.extend_from_slice(unsafe {
+			slice::from_raw_parts(values.as_ptr() as *const u8,
+				mem::size_of_val(values))
+		});
+	}
+}
+
+/// Implement `Std140` for scalars and vectors of `$t`, with a bool's GLSL
+/// representation being a `$t`.
+macro_rules! vectors {
+	($($t: ty),*) => ($(
+		impl Std140 for $t {
+			const ALIGN: usize = 4;
+
+			fn write_std140(&self, writer: &mut Std140Writer) {
+				writer.push(&[*self]);
+			}
+		}
+
+		impl Std140 for [$t; 2] {
+			const ALIGN: usize = 8;
+
+			fn write_std140(&self, writer: &mut Std140Writer) {
+				writer.push(self);
+			}
+		}
+
+		// A vec3 has the alignment of a vec4, but a scalar can follow
+		// it without padding.
+		impl Std140 for [$t; 3] {
+			const ALIGN: usize = 16;
+
+			fn write_std140(&self, writer: &mut Std140Writer) {
+				writer.push(self);
+			}
+		}
+
+		impl Std140 for [$t; 4] {
+			const ALIGN: usize = 16;
+
+			fn write_std140(&self, writer: &mut Std140Writer) {
+				writer.push(self);
+			}
+		}
+	)*)
+}
+
+vectors!(f32, i32, u32);
+
+impl Std140 for bool {
+	const ALIGN: usize = 4;
+
+	fn write_std140(&self, writer: &mut Std140Writer) {
+		writer.push(&[*self as u32]);
+	}
+}
+
+/// Implement `Std140` for a matrix, stored as an array of column vectors.
+macro_rules! matrix {
+	($t: ty) => (
+		impl Std140 for $t {
+			const ALIGN: usize = 16;
+
+			fn write_std140(&self, writer: &mut Std140Writer) {
+				writer.write(&self[..]);
+			}
+		}
+	)
+}
+
+matrix!([[f32; 2]; 2]);
+matrix!([[f32; 3]; 3]);
+matrix!([[f32; 4]; 4]);
+
+/// A mat4, in column-major order.
+impl Std140 for [f32; 16] {
+	const ALIGN: usize = 16;
+
+	fn write_std140(&self, writer: &mut Std140Writer) {
+		writer.push(self);
+	}
+}
+
+/// Arrays have a stride of a multiple of 16 bytes.
+impl<T: Std140> Std140 for [T] {
+	const ALIGN: usize = 16;
+
+	fn write_std140(&self, writer: &mut Std140Writer) {
+		for value in self {
+			writer.write(value).align(16);
+		}
+	}
+}
+
+impl<T: Std140> Std140 for Vec<T> {
+	const ALIGN: usize = 16;
+
+	fn write_std140(&self, writer: &mut Std140Writer) {
+		self[..].write_std140(writer);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The written bytes as floats, padding is 0.0.
+	fn floats<T: Std140 + ?Sized>(value: &T) -> Vec<f32> {
+		Std140Writer::new().write(value).as_bytes().chunks(4)
+			.map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+			.collect()
+	}
+
+	struct Vec3Float {
+		a: [f32; 3],
+		b: f32,
+	}
+
+	std140!(Vec3Float { a, b });
+
+	struct Inner {
+		a: f32,
+		b: [f32; 2],
+	}
+
+	std140!(Inner { a, b });
+
+	struct Outer {
+		x: f32,
+		inner: Inner,
+		y: f32,
+		z: Vec<f32>,
+	}
+
+	std140!(Outer { x, inner, y, z });
+
+	#[test]
+	fn vec3_then_float() {
+		let value = Vec3Float { a: [1.0, 2.0, 3.0], b: 4.0 };
+
+		assert_eq!(floats(&value), [1.0, 2.0, 3.0, 4.0]);
+	}
+
+	#[test]
+	fn array_stride() {
+		assert_eq!(floats(&vec![1.0f32, 2.0]),
+			[1.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0]);
+		assert_eq!(floats(&vec![[1.0f32, 2.0, 3.0]]),
+			[1.0, 2.0, 3.0, 0.0]);
+	}
+
+	#[test]
+	fn matrix_columns() {
+		let mat3 = [[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0],
+			[7.0, 8.0, 9.0]];
+
+		assert_eq!(floats(&mat3), [1.0, 2.0, 3.0, 0.0, 4.0, 5.0, 6.0,
+			0.0, 7.0, 8.0, 9.0, 0.0]);
+		assert_eq!(floats(&[[1.0f32, 2.0], [3.0, 4.0]]),
+			[1.0, 2.0, 0.0, 0.0, 3.0, 4.0, 0.0, 0.0]);
+		assert_eq!(floats(&[1.0f32; 16]).len(), 16);
+	}
+
+	#[test]
+	fn nested_struct() {
+		let value = Outer {
+			x: 1.0,
+			inner: Inner { a: 2.0, b: [3.0, 4.0] },
+			y: 5.0,
+			z: vec![6.0],
+		};
+
+		assert_eq!(floats(&value), [
+			1.0, 0.0, 0.0, 0.0, // x, padded to the struct
+			2.0, 0.0, 3.0, 4.0, // inner.a, inner.b at offset 24
+			5.0, 0.0, 0.0, 0.0, // y, padded to the array
+			6.0, 0.0, 0.0, 0.0, // z[0], and the struct's padding
+		]);
+	}
+
+	#[test]
+	fn bools_are_uints() {
+		let mut writer = Std140Writer::new();
+		let bytes = writer.write(&true).write(&7u32).as_bytes();
+		let values: Vec<u32> = bytes.chunks(4)
+			.map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+			.collect();
+
+		assert_eq!(values, [1, 7]);
+	}
+}
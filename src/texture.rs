@@ -4,23 +4,22 @@
 // https://www.boost.org/LICENSE_1_0.txt)
 
 use std::{ rc::Rc };
-use OpenGL;
+use { Binding, OpenGL };
 use types::*;
 
-static mut CURRENT_TEXTURE: GLuint = 0; // 0 is always invalid texture.
-
 /// An GPU Texture handle.
 #[derive(Clone)] pub struct Texture(Rc<TextureContext>);
 
 impl Texture {
 	pub(crate) fn new(opengl: &OpenGL) -> Self {
 		Texture(Rc::new(TextureContext({
-			let mut a = unsafe { ::std::mem::uninitialized() };
-			gl!(opengl, (opengl.get().gen_textures)(1, &mut a));
-			gl!(opengl, (opengl.get().bind_texture)(GL_TEXTURE_2D, a));
-			gl!(opengl, (opengl.get().tex_params)(GL_TEXTURE_2D,
+			let mut a = 0;
+			gl!(opengl, gen_textures(1, &mut a));
+			gl!(opengl, bind_texture(GL_TEXTURE_2D, a));
+			opengl.get().bound_texture.set(a);
+			gl!(opengl, tex_params(GL_TEXTURE_2D,
 				GL_TEXTURE_MAG_FILTER, GL_LINEAR));
-			gl!(opengl, (opengl.get().tex_params)(GL_TEXTURE_2D,
+			gl!(opengl, tex_params(GL_TEXTURE_2D,
 				GL_TEXTURE_MIN_FILTER, GL_LINEAR_MIPMAP_LINEAR)
 			);
 			a
@@ -28,18 +27,18 @@ impl Texture {
 	}
 
 	/// Set the bound texture's pixels
-	pub fn set(&self, w: u16, h: u16, px: &[u8]) -> () {
+	pub fn set(&self, w: u16, h: u16, px: &[u8]) {
 		self.bind();
-		gl!((*self.0).1, ((*self.0).1.get().tex_image)(GL_TEXTURE_2D, 0,
+		gl!(self.0 .1, tex_image(GL_TEXTURE_2D, 0,
 			GL_RGBA as i32, w as i32, h as i32, 0, GL_RGBA,
 			GL_UNSIGNED_BYTE, px.as_ptr() as *const _));
-		gl!((*self.0).1, ((*self.0).1.get().gen_mipmap)(GL_TEXTURE_2D));
+		gl!(self.0 .1, gen_mipmap(GL_TEXTURE_2D));
 	}
 
 	/// Update the pixels of an already bound & set texture.
-	pub fn update(&self, w: u16, h: u16, px: &[u8]) -> () {
+	pub fn update(&self, w: u16, h: u16, px: &[u8]) {
 		self.bind();
-		gl!((*self.0).1, ((*self.0).1.get().tex_subimage)(GL_TEXTURE_2D,
+		gl!(self.0 .1, tex_subimage(GL_TEXTURE_2D,
 			0, 0, 0, w as i32, h as i32, GL_RGBA, GL_UNSIGNED_BYTE,
 			px.as_ptr() as *const _));
 	}
@@ -48,15 +47,15 @@ impl Texture {
 	pub fn bind(&self) {
 		let texture = self.get();
 
-		if texture != unsafe { CURRENT_TEXTURE } {
-			gl!((*self.0).1, ((*self.0).1.get().bind_texture)(
+		if texture != self.0 .1.get().bound_texture.get() {
+			gl!(self.0 .1, bind_texture(
 				GL_TEXTURE_2D, texture));
-			unsafe { CURRENT_TEXTURE = texture; }
+			self.0 .1.get().bound_texture.set(texture);
 		}
 	}
 
 	pub(crate) fn get(&self) -> u32 {
-		(*self.0).0
+		self.0 .0
 	}
 }
 
@@ -64,6 +63,63 @@ pub struct TextureContext(u32, OpenGL);
 
 impl Drop for TextureContext {
 	fn drop(&mut self) {
-		gl!(self.1, (self.1.get().delete_texture)(1, [self.0].as_ptr()));
+		if self.1.is_destroyed() {
+			return;
+		}
+		self.1.forget_binding(Binding::Texture, self.0);
+		gl!(self.1, delete_texture(1, [self.0].as_ptr()));
+	}
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+	use mock::{ Arg, MockBackend };
+	use types::*;
+
+	/// Get the textures bound by the recorded calls.
+	fn binds(mock: &MockBackend) -> Vec<Arg> {
+		mock.take_calls().into_iter()
+			.filter(|call| call.name == "glBindTexture")
+			.map(|call| {
+				assert_eq!(call.args[0],
+					Arg::Int(GL_TEXTURE_2D.into()));
+				call.args[1].clone()
+			})
+			.collect()
+	}
+
+	#[test]
+	fn bind_skips_redundant_binds() {
+		let mock = MockBackend::new();
+		let opengl = mock.opengl();
+		let a = opengl.texture();
+		let b = opengl.texture();
+		let (a_name, b_name) = (Arg::Int(a.get().into()),
+			Arg::Int(b.get().into()));
+
+		assert_eq!(binds(&mock), vec![a_name.clone(), b_name.clone()]);
+
+		b.bind();
+		a.bind();
+		a.bind();
+		b.set(1, 1, &[0; 4]);
+		assert_eq!(binds(&mock), vec![a_name.clone(), b_name]);
+
+		opengl.invalidate_bindings();
+		a.bind();
+		a.bind();
+		assert_eq!(binds(&mock), vec![a_name]);
+	}
+
+	#[test]
+	fn drop_forgets_binding() {
+		let mock = MockBackend::new();
+		let opengl = mock.opengl();
+		let a = opengl.texture();
+		let name = a.get();
+
+		drop(a);
+		assert_ne!(opengl.get().bound_texture.get(), name);
+		assert_eq!(mock.names().last(), Some(&"glDeleteTextures"));
 	}
 }
@@ -15,6 +15,7 @@ use c_void;
 #[allow(unused)] pub type GLbitfield = u32;
 #[allow(unused)] pub type GLsizeiptr = isize;
 #[allow(unused)] pub type GLfloat = f32;
+#[allow(unused)] pub type GLdouble = f64;
 #[allow(unused)] pub type GLubyte = u8;
 
 // X11 & Android
@@ -29,6 +30,12 @@ use c_void;
 #[allow(unused)] pub type EGLenum = u32;
 
 #[allow(unused)] pub const GL_FLOAT: u32 = 0x1406;
+#[allow(unused)] pub const GL_RENDERER: u32 = 0x1F01;
+#[allow(unused)] pub const GL_VERSION: u32 = 0x1F02;
+#[allow(unused)] pub const GL_EXTENSIONS: u32 = 0x1F03;
+#[allow(unused)] pub const GL_CONTEXT_PROFILE_MASK: u32 = 0x9126;
+#[allow(unused)] pub const GL_PROGRAM_BINARY_RETRIEVABLE_HINT: u32 = 0x8257;
+#[allow(unused)] pub const GL_PROGRAM_BINARY_LENGTH: u32 = 0x8741;
 #[allow(unused)] pub const GL_TEXTURE_2D: u32 = 0x0DE1;
 #[allow(unused)] pub const GL_TEXTURE_MAG_FILTER: u32 = 0x2800;
 #[allow(unused)] pub const GL_TEXTURE_MIN_FILTER: u32 = 0x2801;
@@ -43,6 +50,15 @@ use c_void;
 #[allow(unused)] pub const GL_ELEMENT_ARRAY_BUFFER: u32 = 0x8893;
 #[allow(unused)] pub const GL_ARRAY_BUFFER: u32 = 0x8892;
 #[allow(unused)] pub const GL_DYNAMIC_DRAW: u32 = 0x88E8;
+#[allow(unused)] pub const GL_UNIFORM_BUFFER: u32 = 0x8A11;
+#[allow(unused)] pub const GL_INVALID_INDEX: u32 = 0xFFFF_FFFF;
+#[allow(unused)] pub const GL_COMPILE_STATUS: u32 = 0x8B81;
+#[allow(unused)] pub const GL_LINK_STATUS: u32 = 0x8B82;
+#[allow(unused)] pub const GL_INFO_LOG_LENGTH: u32 = 0x8B84;
+#[allow(unused)] pub const GL_ACTIVE_UNIFORMS: u32 = 0x8B86;
+#[allow(unused)] pub const GL_ACTIVE_UNIFORM_MAX_LENGTH: u32 = 0x8B87;
+#[allow(unused)] pub const GL_ACTIVE_ATTRIBUTES: u32 = 0x8B89;
+#[allow(unused)] pub const GL_ACTIVE_ATTRIBUTE_MAX_LENGTH: u32 = 0x8B8A;
 
 #[allow(unused)] pub const EGL_BUFFER_SIZE: i32 = 0x3020;
 #[allow(unused)] pub const EGL_ALPHA_SIZE: i32 = 0x3021;
@@ -110,6 +126,27 @@ use c_void;
 #[allow(unused)] pub const EGL_CONTEXT_CLIENT_VERSION: i32 = 0x3098;
 
 #[allow(unused)] pub const EGL_OPENGL_ES_API: u32 = 0x30A0;
+#[allow(unused)] pub const EGL_OPENGL_API: u32 = 0x30A2;
+
+#[allow(unused)] pub const EGL_CONTEXT_MAJOR_VERSION: i32 = 0x3098;
+#[allow(unused)] pub const EGL_CONTEXT_MINOR_VERSION: i32 = 0x30FB;
+#[allow(unused)] pub const EGL_CONTEXT_OPENGL_PROFILE_MASK: i32 = 0x30FD;
+#[allow(unused)] pub const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: i32 = 0x01;
+#[allow(unused)] pub const EGL_CONTEXT_OPENGL_COMPATIBILITY_PROFILE_BIT: i32 =
+	0x02;
+
+#[allow(unused)] pub const EGL_PLATFORM_X11_KHR: u32 = 0x31D5;
+#[allow(unused)] pub const EGL_PLATFORM_GBM_KHR: u32 = 0x31D7;
+#[allow(unused)] pub const EGL_PLATFORM_WAYLAND_KHR: u32 = 0x31D8;
+#[allow(unused)] pub const EGL_PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
+#[allow(unused)] pub const EGL_PLATFORM_DEVICE_EXT: u32 = 0x313F;
+#[allow(unused)] pub const EGL_TRACK_REFERENCES_KHR: i32 = 0x3352;
+
+#[allow(unused)] pub const EGL_DEFAULT_DISPLAY: EGLNativeDisplayType =
+	::std::ptr::null_mut();
+#[allow(unused)] pub const EGL_NO_DISPLAY: EGLDisplay = ::std::ptr::null_mut();
+#[allow(unused)] pub const EGL_NO_SURFACE: EGLSurface = ::std::ptr::null_mut();
+#[allow(unused)] pub const EGL_NO_CONTEXT: EGLContext = ::std::ptr::null_mut();
 
 #[allow(dead_code)] #[repr(C)] pub struct PixelFormatDescriptor {
 	pub n_size: u16, // word
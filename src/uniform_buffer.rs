@@ -0,0 +1,76 @@
+// Copyright Jeron A. Lau 2018.
+// Dual-licensed under either the MIT License or the Boost Software License,
+// Version 1.0.  (See accompanying file LICENSE_1_0.txt or copy at
+// https://www.boost.org/LICENSE_1_0.txt)
+
+use { Buffer, Capability, OpenGL, Std140, Std140Writer };
+use types::*;
+
+/// A buffer of uniforms shared between programs, with the std140 layout.
+/// Needs OpenGL ES 3.0.
+#[derive(Clone)] pub struct UniformBuffer(Buffer);
+
+impl UniformBuffer {
+	/// Create a new uniform buffer, `None` if they aren't supported.
+	pub fn new(opengl: &OpenGL) -> Option<Self> {
+		if !opengl.has(Capability::UniformBuffer) {
+			return None;
+		}
+
+		Some(UniformBuffer(Buffer::new(opengl)))
+	}
+
+	/// Set the buffer's data.
+	pub fn set<T: Std140 + ?Sized>(&self, value: &T) {
+		let mut writer = Std140Writer::new();
+
+		writer.write(value).align(16);
+		self.set_bytes(writer.as_bytes());
+	}
+
+	/// Set the buffer's data, already in the std140 layout.
+	pub fn set_bytes(&self, data: &[u8]) {
+		let opengl = &self.0 .0 .1;
+
+		// Binding to GL_UNIFORM_BUFFER doesn't change GL_ARRAY_BUFFER,
+		// so `bound_buffer` stays correct.
+		gl!(opengl, bind_buffer(GL_UNIFORM_BUFFER, self.0.get()));
+		gl!(opengl, buffer_data(GL_UNIFORM_BUFFER, data.len() as isize,
+			data.as_ptr() as *const _, GL_DYNAMIC_DRAW));
+	}
+
+	/// Bind this buffer to the binding point `binding`, for the uniform
+	/// blocks bound to it with `Program::bind_uniform_block()`.
+	pub fn bind(&self, binding: u32) {
+		gl!(self.0 .0 .1, ?bind_buffer_base(GL_UNIFORM_BUFFER, binding,
+			self.0.get()));
+	}
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+	use mock::MockBackend;
+	use { ErrorPolicy, Program, UniformBuffer };
+
+	#[test]
+	#[cfg(any(debug_assertions, feature = "checked"))]
+	fn errors_name_the_function() {
+		let mock = MockBackend::new();
+		mock.set_version("OpenGL ES 3.0 Mock");
+		mock.set_uniform_blocks(&["Lights"]);
+		let opengl = mock.opengl();
+		let program = Program::new(&opengl, b"", b"");
+		let buffer = UniformBuffer::new(&opengl).unwrap();
+
+		opengl.set_error_policy(ErrorPolicy::Collect);
+		mock.fail("glUniformBlockBinding", 0x0501);
+		mock.fail("glBindBufferBase", 0x0501);
+		assert!(program.bind_uniform_block(b"Lights\0", 1));
+		buffer.bind(1);
+
+		let functions: Vec<_> = opengl.errors().into_iter()
+			.map(|error| error.function).collect();
+		assert_eq!(functions, ["glUniformBlockBinding",
+			"glBindBufferBase"]);
+	}
+}
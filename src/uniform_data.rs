@@ -3,71 +3,545 @@
 // Version 1.0.  (See accompanying file LICENSE_1_0.txt or copy at
 // https://www.boost.org/LICENSE_1_0.txt)
 
-use Program;
+use std::{ any::type_name, cell::Cell, mem, slice };
+use { Capability, GlslType, OpenGL, Program, UniformError };
 use types::*;
 
 /// Uniform Data handle for a GPU Program
-pub struct UniformData(pub(crate) GLint, Program);
+pub struct UniformData {
+	program: Program,
+	name: String,
+	located: Cell<Located>,
+}
+
+/// Where a uniform is, in a version of the program.
+#[derive(Copy, Clone)]
+struct Located {
+	location: GLint,
+	// `None` if the uniform isn't active.
+	kind: Option<GlslType>,
+	// The number of elements from this one to the end of the array.
+	size: i32,
+	// The index in `Program::uniforms()`, and the array element.
+	index: Option<usize>,
+	element: i32,
+	// The `Program::generation()` it was looked up in.
+	generation: u32,
+}
 
 impl UniformData {
 	/// Get uniform from a shader.
 	pub fn new(program: &Program, name: &[u8]) -> Self {
 		// Last character in slice needs to null for it to be safe.
 		assert_eq!(name[name.len() -1], b'\0');
-		let opengl = program.opengl();
-		let r = gl!(opengl, (opengl.get().uniform)(program.get(),
-			name.as_ptr() as *const _));
-		UniformData(r, program.clone())
+		let name = String::from_utf8_lossy(&name[..name.len() - 1])
+			.into_owned();
+		let located = Cell::new(locate(program, &name));
+
+		UniformData { program: program.clone(), name, located }
 	}
 
 	/// If there is no such VertexData handle.
 	pub fn is_none(&self) -> bool {
-		self.0 == -1
+		self.located().location == -1
+	}
+
+	/// Get the GLSL type of the uniform, `None` if it isn't active.
+	pub fn kind(&self) -> Option<GlslType> {
+		self.located().kind
+	}
+
+	/// Get where the uniform is, looking it up again if the program was
+	/// reloaded.
+	fn located(&self) -> Located {
+		let mut located = self.located.get();
+
+		if located.generation != self.program.generation() {
+			located = locate(&self.program, &self.name);
+			self.located.set(located);
+		}
+		located
+	}
+
+	/// Set the uniform, or get an error if its GLSL type doesn't match
+	/// the value.  A slice sets the elements of an array, starting at
+	/// this one.  Does nothing if the uniform isn't active, or already
+	/// has this value.
+	pub fn set<T: UniformValue>(&self, value: T) -> Result<(), UniformError>
+	{
+		let located = self.located();
+
+		if let Some(kind) = located.kind {
+			if !T::accepts(kind, &self.program.opengl()) {
+				return Err(UniformError::Type {
+					name: self.name.clone(),
+					kind,
+					value: type_name::<T>(),
+				});
+			}
+			if value.count() > located.size.max(0) as usize {
+				return Err(UniformError::Count {
+					name: self.name.clone(),
+					size: located.size,
+					count: value.count(),
+				});
+			}
+		}
+		if located.location == -1 || value.count() == 0 {
+			return Ok(());
+		}
+		if let Some(index) = located.index {
+			if !self.program.cache_uniform(index, located.element,
+				value.count(), value.transposed(), value.bytes())
+			{
+				return Ok(());
+			}
+		}
+
+		self.program.bind(); // bind the program attached to this uniform.
+		value.upload(&self.program.opengl(), located.location, false);
+		Ok(())
+	}
+
+	/// Set the uniform, with a type mismatch being a panic if errors are
+	/// checked, and skipped otherwise.
+	fn set_checked<T: UniformValue>(&self, value: T) {
+		if let Err(_error) = self.set(value) {
+			#[cfg(any(debug_assertions, feature = "checked"))]
+			panic!("{}", _error);
+		}
 	}
 
 	/// Set a mat4 uniform
-	pub fn set_mat4(&self, mat4: [f32; 16]) -> () {
-		self.1.bind(); // bind the program attached to this uniform.
-		let opengl = self.1.opengl();
-		gl!(opengl, (opengl.get().uniform_mat4)(self.0, 1,
-			0 /*bool: transpose*/, mat4.as_ptr()));
+	pub fn set_mat4(&self, mat4: [f32; 16]) {
+		self.set_checked(mat4);
 	}
 
-	/// Set an int uniform 
-	pub fn set_int1(&self, int1: i32) -> () {
-		self.1.bind(); // bind the program attached to this uniform.
-		let opengl = self.1.opengl();
-		gl!(opengl, (opengl.get().uniform_int1)(self.0, int1));
+	/// Set an int uniform
+	pub fn set_int1(&self, int1: i32) {
+		self.set_checked(int1);
 	}
 
 	/// Set a float uniform
-	pub fn set_vec1(&self, vec1: f32) -> () {
-		self.1.bind(); // bind the program attached to this uniform.
-		let opengl = self.1.opengl();
-		gl!(opengl, (opengl.get().uniform_vec1)(self.0, vec1));
+	pub fn set_vec1(&self, vec1: f32) {
+		self.set_checked(vec1);
 	}
 
 	/// Set a vec2 uniform
-	pub fn set_vec2(&self, vec: &[f32; 2]) -> () {
-		self.1.bind(); // bind the program attached to this uniform.
-		let opengl = self.1.opengl();
-		gl!(opengl, (opengl.get().uniform_vec2)(self.0, vec[0],
-			vec[1]));
+	pub fn set_vec2(&self, vec: &[f32; 2]) {
+		self.set_checked(*vec);
 	}
 
 	/// Set a vec3 uniform
-	pub fn set_vec3(&self, vec: &[f32; 3]) -> () {
-		self.1.bind(); // bind the program attached to this uniform.
-		let opengl = self.1.opengl();
-		gl!(opengl, (opengl.get().uniform_vec3)(self.0, vec[0],
-			vec[1], vec[2]));
+	pub fn set_vec3(&self, vec: &[f32; 3]) {
+		self.set_checked(*vec);
 	}
 
 	/// Set a vec4 uniform
-	pub fn set_vec4(&self, vec: &[f32; 4]) -> () {
-		self.1.bind(); // bind the program attached to this uniform.
-		let opengl = self.1.opengl();
-		gl!(opengl, (opengl.get().uniform_vec4)(self.0, vec[0],
-			vec[1], vec[2], vec[3]));
+	pub fn set_vec4(&self, vec: &[f32; 4]) {
+		self.set_checked(*vec);
+	}
+}
+
+/// Look up the uniform `name` in the program.
+fn locate(program: &Program, name: &str) -> Located {
+	let opengl = program.opengl();
+	let c_name = format!("{}\0", name);
+	let location = gl!(opengl, uniform(program.get(),
+		c_name.as_ptr() as *const _));
+	// Arrays are reflected as "name[0]", for any element.
+	let (array, element) = match name.find('[') {
+		Some(i) => (format!("{}[0]", &name[..i]), name[i + 1..]
+			.trim_end_matches(']').parse().unwrap_or(0)),
+		None => (format!("{}[0]", name), 0),
+	};
+	let uniforms = program.uniforms();
+	let index = uniforms.iter()
+		.position(|u| u.name == name || u.name == array);
+
+	Located {
+		location,
+		kind: index.map(|i| uniforms[i].kind),
+		size: index.map(|i| uniforms[i].size - element).unwrap_or(1),
+		index,
+		element,
+		generation: program.generation(),
+	}
+}
+
+mod sealed {
+	use { GlslType, OpenGL };
+	use types::*;
+
+	pub trait Sealed {}
+
+	/// A value that can also be uploaded as an array.
+	pub trait Element: Copy {
+		fn accepts(kind: GlslType, opengl: &OpenGL) -> bool;
+
+		fn upload(&self, opengl: &OpenGL, location: GLint,
+			transpose: bool);
+
+		fn upload_slice(values: &[Self], opengl: &OpenGL,
+			location: GLint, transpose: bool);
+	}
+}
+
+/// A value that a uniform can be set to with `UniformData::set()`.
+pub trait UniformValue: sealed::Sealed {
+	/// If a uniform of GLSL type `kind` can be set to this value, with the
+	/// functions that `opengl` supports.
+	fn accepts(kind: GlslType, opengl: &OpenGL) -> bool;
+
+	#[doc(hidden)]
+	fn count(&self) -> usize {
+		1
+	}
+
+	#[doc(hidden)]
+	fn bytes(&self) -> &[u8];
+
+	#[doc(hidden)]
+	fn transposed(&self) -> bool {
+		false
+	}
+
+	#[doc(hidden)]
+	fn upload(&self, opengl: &OpenGL, location: GLint, transpose: bool);
+}
+
+/// How many uniform uploads were skipped, because the uniform already had
+/// the value, and how many were made.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct UniformStats {
+	pub hits: u64,
+	pub misses: u64,
+}
+
+/// Get the bytes of plain values, to compare them with the cached value.
+fn as_bytes<T: Copy>(values: &[T]) -> &[u8] {
+	// Only used for bools, and arrays of f32, i32 & u32, which have no
+	// padding.
+	unsafe {
+		slice::from_raw_parts(values.as_ptr() as *const u8,
+			mem::size_of_val(values))
+	}
+}
+
+impl<T: sealed::Element> sealed::Sealed for T {}
+impl<T: sealed::Element> UniformValue for T {
+	fn accepts(kind: GlslType, opengl: &OpenGL) -> bool {
+		<T as sealed::Element>::accepts(kind, opengl)
+	}
+
+	fn bytes(&self) -> &[u8] {
+		as_bytes(slice::from_ref(self))
+	}
+
+	fn upload(&self, opengl: &OpenGL, location: GLint, transpose: bool) {
+		sealed::Element::upload(self, opengl, location, transpose);
+	}
+}
+
+impl<T: sealed::Element> sealed::Sealed for &[T] {}
+impl<T: sealed::Element> UniformValue for &[T] {
+	fn accepts(kind: GlslType, opengl: &OpenGL) -> bool {
+		T::accepts(kind, opengl)
+	}
+
+	fn count(&self) -> usize {
+		self.len()
+	}
+
+	fn bytes(&self) -> &[u8] {
+		as_bytes(self)
+	}
+
+	fn upload(&self, opengl: &OpenGL, location: GLint, transpose: bool) {
+		T::upload_slice(self, opengl, location, transpose);
+	}
+}
+
+/// A matrix (or slice of matrices) in row-major order, transposed when it's
+/// uploaded.  Needs OpenGL ES 3.0 (or desktop OpenGL).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transposed<T>(pub T);
+
+impl<T: UniformValue> sealed::Sealed for Transposed<T> {}
+impl<T: UniformValue> UniformValue for Transposed<T> {
+	fn accepts(kind: GlslType, opengl: &OpenGL) -> bool {
+		// OpenGL ES 2.0 only allows `transpose` to be false.
+		T::accepts(kind, opengl) && matches!(kind, GlslType::Mat2
+			| GlslType::Mat3 | GlslType::Mat4)
+			&& opengl.version().at_least((3, 0), (2, 0))
+	}
+
+	fn count(&self) -> usize {
+		self.0.count()
+	}
+
+	fn bytes(&self) -> &[u8] {
+		self.0.bytes()
+	}
+
+	fn transposed(&self) -> bool {
+		true
+	}
+
+	fn upload(&self, opengl: &OpenGL, location: GLint, _: bool) {
+		self.0.upload(opengl, location, true);
+	}
+}
+
+/// Implement `Element` for a scalar or vector uploaded with `[$one]` (taking
+/// the components at `$i`, or the scalar) or `[$many]` (taking an array).
+/// It accepts the `$kind`s, and any type that `GlslType::$also()` is true
+/// for, if the context has `Capability::$needs`.
+macro_rules! vector {
+	($t: ty, $($kind: ident)|* $(, if $also: ident)*
+		$(, needs $needs: ident)*,
+		[$($one: tt)+]($($i: tt),*), [$($many: tt)+]) =>
+	(
+		element!($t, $($kind)|* $(, if $also)* $(, needs $needs)*,
+			[$($many)+], |value, opengl, location| gl!(opengl,
+				$($one)+(location, $(value[$i]),*)));
+	);
+	($t: ty, $($kind: ident)|* $(, if $also: ident)*
+		$(, needs $needs: ident)*, [$($one: tt)+],
+		[$($many: tt)+]) =>
+	(
+		element!($t, $($kind)|* $(, if $also)* $(, needs $needs)*,
+			[$($many)+], |value, opengl, location| gl!(opengl,
+				$($one)+(location, *value)));
+	)
+}
+
+/// Implement `Element` for `$t`, with `$one` uploading a `$value`.
+macro_rules! element {
+	($t: ty, $($kind: ident)|* $(, if $also: ident)*
+		$(, needs $needs: ident)*, [$($many: tt)+],
+		|$value: ident, $opengl: ident, $location: ident| $one: expr) =>
+	(
+		impl sealed::Element for $t {
+			fn accepts(kind: GlslType, _opengl: &OpenGL) -> bool {
+				(matches!(kind, $(GlslType::$kind)|*)
+					$(|| kind.$also())*)
+					$(&& _opengl.has(Capability::$needs))*
+			}
+
+			fn upload(&self, $opengl: &OpenGL, $location: GLint,
+				_: bool)
+			{
+				let $value = self;
+				$one;
+			}
+
+			fn upload_slice(values: &[Self], opengl: &OpenGL,
+				location: GLint, _: bool)
+			{
+				gl!(opengl, $($many)+(location,
+					values.len() as GLsizei,
+					values.as_ptr() as *const _));
+			}
+		}
+	)
+}
+
+/// Implement `Element` for a matrix uploaded with `$f`.
+macro_rules! matrix {
+	($t: ty, $kind: ident, $f: ident) => (
+		impl sealed::Element for $t {
+			fn accepts(kind: GlslType, _: &OpenGL) -> bool {
+				kind == GlslType::$kind
+			}
+
+			fn upload(&self, opengl: &OpenGL, location: GLint,
+				transpose: bool)
+			{
+				sealed::Element::upload_slice(&[*self], opengl,
+					location, transpose);
+			}
+
+			fn upload_slice(values: &[Self], opengl: &OpenGL,
+				location: GLint, transpose: bool)
+			{
+				gl!(opengl, $f(location, values.len() as GLsizei,
+					transpose as GLboolean,
+					values.as_ptr() as *const _));
+			}
+		}
+	)
+}
+
+vector!(f32, Float | Bool, [uniform_vec1], [uniform_vecs1]);
+vector!([f32; 2], Vec2 | BVec2, [uniform_vec2](0, 1), [uniform_vecs2]);
+vector!([f32; 3], Vec3 | BVec3, [uniform_vec3](0, 1, 2), [uniform_vecs3]);
+vector!([f32; 4], Vec4 | BVec4, [uniform_vec4](0, 1, 2, 3),
+	[uniform_vecs4]);
+// Also accepts samplers, to set their texture unit.
+vector!(i32, Int | Bool, if is_sampler, [uniform_int1], [uniform_ints1]);
+vector!([i32; 2], IVec2 | BVec2, [uniform_int2](0, 1), [uniform_ints2]);
+vector!([i32; 3], IVec3 | BVec3, [uniform_int3](0, 1, 2), [uniform_ints3]);
+vector!([i32; 4], IVec4 | BVec4, [uniform_int4](0, 1, 2, 3),
+	[uniform_ints4]);
+// Unsigned types only exist in OpenGL ES 3.0, but bools could be set without
+// these functions.
+vector!(u32, UInt | Bool, needs UnsignedUniform, [?uniform_uint1],
+	[?uniform_uints1]);
+vector!([u32; 2], UVec2 | BVec2, needs UnsignedUniform,
+	[?uniform_uint2](0, 1), [?uniform_uints2]);
+vector!([u32; 3], UVec3 | BVec3, needs UnsignedUniform,
+	[?uniform_uint3](0, 1, 2), [?uniform_uints3]);
+vector!([u32; 4], UVec4 | BVec4, needs UnsignedUniform,
+	[?uniform_uint4](0, 1, 2, 3), [?uniform_uints4]);
+matrix!([[f32; 2]; 2], Mat2, uniform_mat2);
+matrix!([[f32; 3]; 3], Mat3, uniform_mat3);
+matrix!([[f32; 4]; 4], Mat4, uniform_mat4);
+matrix!([f32; 16], Mat4, uniform_mat4);
+
+impl sealed::Sealed for bool {}
+impl UniformValue for bool {
+	fn accepts(kind: GlslType, _: &OpenGL) -> bool {
+		kind == GlslType::Bool
+	}
+
+	fn bytes(&self) -> &[u8] {
+		as_bytes(slice::from_ref(self))
+	}
+
+	fn upload(&self, opengl: &OpenGL, location: GLint, _: bool) {
+		gl!(opengl, uniform_int1(location, *self as GLint));
+	}
+}
+
+impl sealed::Sealed for [bool; 2] {}
+impl UniformValue for [bool; 2] {
+	fn accepts(kind: GlslType, _: &OpenGL) -> bool {
+		kind == GlslType::BVec2
+	}
+
+	fn bytes(&self) -> &[u8] {
+		as_bytes(slice::from_ref(self))
+	}
+
+	fn upload(&self, opengl: &OpenGL, location: GLint, _: bool) {
+		gl!(opengl, uniform_int2(location, self[0] as GLint,
+			self[1] as GLint));
+	}
+}
+
+impl sealed::Sealed for [bool; 3] {}
+impl UniformValue for [bool; 3] {
+	fn accepts(kind: GlslType, _: &OpenGL) -> bool {
+		kind == GlslType::BVec3
+	}
+
+	fn bytes(&self) -> &[u8] {
+		as_bytes(slice::from_ref(self))
+	}
+
+	fn upload(&self, opengl: &OpenGL, location: GLint, _: bool) {
+		gl!(opengl, uniform_int3(location, self[0] as GLint,
+			self[1] as GLint, self[2] as GLint));
+	}
+}
+
+impl sealed::Sealed for [bool; 4] {}
+impl UniformValue for [bool; 4] {
+	fn accepts(kind: GlslType, _: &OpenGL) -> bool {
+		kind == GlslType::BVec4
+	}
+
+	fn bytes(&self) -> &[u8] {
+		as_bytes(slice::from_ref(self))
+	}
+
+	fn upload(&self, opengl: &OpenGL, location: GLint, _: bool) {
+		gl!(opengl, uniform_int4(location, self[0] as GLint,
+			self[1] as GLint, self[2] as GLint, self[3] as GLint));
+	}
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+	use mock::{ Arg, MockBackend };
+	use { GlslType, Program, Transposed, UniformError };
+
+	const SAMPLER_EXTERNAL_OES: u32 = 0x8D66;
+	const SAMPLER_2D_SHADOW: u32 = 0x8B62;
+
+	#[test]
+	fn samplers_take_texture_units() {
+		let mock = MockBackend::new();
+		mock.set_uniforms(&[
+			("tex", GlslType::Other(SAMPLER_EXTERNAL_OES), 1),
+			("shadows[0]", GlslType::Other(SAMPLER_2D_SHADOW), 2),
+		]);
+		let opengl = mock.opengl();
+		let program = Program::new(&opengl, b"", b"");
+		let tex = program.uniform(b"tex\0");
+		let shadows = program.uniform(b"shadows\0");
+
+		assert!(GlslType::Other(SAMPLER_EXTERNAL_OES).is_sampler());
+		assert!(GlslType::Sampler2D.is_sampler());
+		assert!(!GlslType::Other(0x8B65).is_sampler()); // mat2x3
+		mock.take_calls();
+		assert_eq!(tex.set(0i32), Ok(()));
+		tex.set_int1(1);
+		assert_eq!(shadows.set(&[2i32, 3][..]), Ok(()));
+
+		let calls: Vec<_> = mock.take_calls().into_iter()
+			.filter(|call| call.name.starts_with("glUniform"))
+			.map(|call| (call.name, call.args.last().cloned()))
+			.collect();
+		assert_eq!(calls, [
+			("glUniform1i", Some(Arg::Int(0))),
+			("glUniform1i", Some(Arg::Int(1))),
+			("glUniform1iv", Some(Arg::Ints(vec![2, 3]))),
+		]);
+
+		match tex.set(0.0f32) {
+			Err(UniformError::Type { kind, .. }) => {
+				assert_eq!(kind, GlslType::Other(
+					SAMPLER_EXTERNAL_OES));
+			}
+			result => panic!("{:?}", result),
+		}
+	}
+
+	#[test]
+	fn needs_opengl_es_3() {
+		let mock = MockBackend::new();
+		mock.set_uniforms(&[
+			("flag", GlslType::Bool, 1),
+			("m", GlslType::Mat2, 1),
+		]);
+		let opengl = mock.opengl();
+		let program = Program::new(&opengl, b"", b"");
+		let flag = program.uniform(b"flag\0");
+		let m = program.uniform(b"m\0");
+
+		// No `glUniform1ui()` or `transpose` on OpenGL ES 2.0.
+		assert!(matches!(flag.set(1u32),
+			Err(UniformError::Type { .. })));
+		assert!(matches!(m.set(Transposed([[1.0f32, 2.0], [3.0, 4.0]])),
+			Err(UniformError::Type { .. })));
+		assert_eq!(opengl.uniform_stats().misses, 0);
+		assert_eq!(flag.set(1i32), Ok(()));
+		assert_eq!(m.set([[1.0f32, 2.0], [3.0, 4.0]]), Ok(()));
+
+		let mock = MockBackend::new();
+		mock.set_version("OpenGL ES 3.0 Mock");
+		mock.set_uniforms(&[
+			("flag", GlslType::Bool, 1),
+			("m", GlslType::Mat2, 1),
+		]);
+		let opengl = mock.opengl();
+		let program = Program::new(&opengl, b"", b"");
+
+		assert_eq!(program.uniform(b"flag\0").set(1u32), Ok(()));
+		assert_eq!(program.uniform(b"m\0").set(Transposed(
+			[[1.0f32, 2.0], [3.0, 4.0]])), Ok(()));
+		assert!(mock.names().contains(&"glUniform1ui"));
 	}
 }
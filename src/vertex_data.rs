@@ -17,10 +17,10 @@ impl VertexData {
 		// Last character in slice needs to null for it to be safe.
 		assert_eq!(name[name.len() -1], b'\0');
 		let opengl = program.opengl();
-		let attrib = gl!(opengl, (opengl.get().vdata)(program.get(),
+		let attrib = gl!(opengl, vdata(program.get(),
 			name.as_ptr() as *const _));
 		if attrib != -1 {
-			gl!(opengl, (opengl.get().enable_vdata)(attrib as u32));
+			gl!(opengl, enable_vdata(attrib as u32));
 		}
 		VertexData(Rc::new(VertexDataContext(attrib, Cell::new(None),
 			program.clone())))
@@ -38,7 +38,7 @@ impl VertexData {
 		self.0 .1.set(Some(buffer.clone()));
 		// Set to the new buffer.
 		buffer.bind();
-		gl!(opengl, (opengl.get().vertex_attrib)(self.0 .0 as GLuint, 4,
+		gl!(opengl, vertex_attrib(self.0 .0 as GLuint, 4,
 			GL_FLOAT, 0, 0, ptr::null()));
 	}
 }